@@ -4,14 +4,20 @@ use crate::dom_tree::NodeData;
 use crate::dom_tree::NodeId;
 use crate::dom_tree::NodeRef;
 use crate::dom_tree::Tree;
-use html5ever::parse_document;
+use crate::parser::ParseOptions;
+use crate::position::{ParseError, SourcePosition};
+use encoding_rs::Encoding;
+#[cfg(feature = "async")]
+use futures_io::AsyncRead;
 use markup5ever::interface::tree_builder;
 use markup5ever::interface::tree_builder::{ElementFlags, NodeOrText, QuirksMode, TreeSink};
 use markup5ever::Attribute;
 use markup5ever::ExpandedName;
 use markup5ever::QualName;
+use selectors::matching;
 use std::borrow::Cow;
 use std::collections::HashSet;
+use std::io::{self, Read};
 use tendril::StrTendril;
 use tendril::TendrilSink;
 
@@ -21,10 +27,25 @@ pub struct Document {
     pub(crate) tree: Tree<NodeData>,
 
     /// Errors that occurred during parsing.
-    pub errors: Vec<Cow<'static, str>>,
+    pub errors: Vec<ParseError>,
 
-    /// The document's quirks mode.
+    /// The document's quirks mode. In quirks mode, selectors match ids and
+    /// classes case-insensitively, like browsers do.
     pub quirks_mode: QuirksMode,
+
+    /// The character encoding the document was decoded from. Documents parsed
+    /// from strings are always UTF-8.
+    pub encoding: &'static Encoding,
+
+    /// The line the tokenizer is on while parsing.
+    current_line: u64,
+
+    /// Where the token being processed starts, or where the tokenizer found
+    /// an error, while positions are tracked.
+    pub(crate) position: Option<SourcePosition>,
+
+    /// The last element created.
+    pub(crate) created: Option<NodeId>,
 }
 
 impl Default for Document {
@@ -33,19 +54,23 @@ impl Default for Document {
             tree: Tree::new(NodeData::Document),
             errors: vec![],
             quirks_mode: tree_builder::NoQuirks,
+            encoding: encoding_rs::UTF_8,
+            current_line: 1,
+            position: None,
+            created: None,
         }
     }
 }
 
 impl From<&str> for Document {
     fn from(html: &str) -> Document {
-        parse_document(Document::default(), Default::default()).one(html)
+        ParseOptions::default().parse(html)
     }
 }
 
 impl From<StrTendril> for Document {
     fn from(html: StrTendril) -> Document {
-        parse_document(Document::default(), Default::default()).one(html)
+        ParseOptions::default().parse(html)
     }
 }
 
@@ -56,6 +81,112 @@ impl From<&String> for Document {
 }
 
 impl Document {
+    /// Parses an HTML fragment as if it were the contents of an element named
+    /// `context`, e.g. `"tr"` for table cells or `"body"` for flow content.
+    ///
+    /// Unlike `Document::from`, no `<html>`, `<head>` or `<body>` shell is
+    /// created around the fragment, and the parsed nodes become the children
+    /// of the document node.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use nipper::Document;
+    ///
+    /// let document = Document::fragment("<td>a</td><td>b</td>", "tr");
+    /// assert_eq!(document.select("td").length(), 2);
+    /// assert_eq!(document.html().to_string(), "<td>a</td><td>b</td>");
+    /// ```
+    pub fn fragment<T>(html: T, context: &str) -> Document
+    where
+        T: Into<StrTendril>,
+    {
+        ParseOptions::default().parse_fragment(html, context)
+    }
+
+    /// Parses an HTML document from bytes, detecting the character encoding
+    /// from the byte order mark or a `<meta>` declaration. The detected
+    /// encoding is recorded in `Document::encoding`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use nipper::Document;
+    ///
+    /// let bytes = b"<meta charset=\"windows-1252\"><p>caf\xE9</p>";
+    /// let document = Document::from_bytes(bytes);
+    /// assert_eq!(document.encoding.name(), "windows-1252");
+    /// assert_eq!(document.select("p").text().to_string(), "café");
+    /// ```
+    pub fn from_bytes(bytes: &[u8]) -> Document {
+        Document::from_bytes_with_charset(bytes, None)
+    }
+
+    /// Works like `from_bytes` but takes the charset given by the transport
+    /// layer into account, e.g. the HTTP `Content-Type` header. The hint can be
+    /// either a charset label like `"Shift_JIS"` or a whole header value like
+    /// `"text/html; charset=Shift_JIS"`. A byte order mark still takes
+    /// precedence over the hint, and the hint over `<meta>` declarations.
+    pub fn from_bytes_with_charset(bytes: &[u8], transport_charset: Option<&str>) -> Document {
+        ParseOptions::default().parse_bytes(bytes, transport_charset)
+    }
+
+    /// Parses an HTML document from a reader like `from_bytes`, without
+    /// reading the whole input into memory first. See `ParseOptions::parser`
+    /// for feeding chunks yourself.
+    pub fn from_reader<R: Read>(reader: R) -> io::Result<Document> {
+        ParseOptions::default().parse_reader(reader)
+    }
+
+    /// Parses an HTML document from an asynchronous reader like `from_reader`,
+    /// parsing each chunk as soon as it arrives. Tokio readers can be adapted
+    /// with `tokio_util::compat`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use nipper::Document;
+    ///
+    /// # futures::executor::block_on(async {
+    /// let reader = futures::io::Cursor::new(b"<p>Hello</p>".to_vec());
+    /// let document = Document::from_async_reader(reader).await.unwrap();
+    /// assert_eq!(document.select("p").text().to_string(), "Hello");
+    /// # });
+    /// ```
+    #[cfg(feature = "async")]
+    pub async fn from_async_reader<R>(reader: R) -> io::Result<Document>
+    where
+        R: AsyncRead + Unpin,
+    {
+        ParseOptions::default().parse_async_reader(reader).await
+    }
+
+    /// Parses an XML document, such as an RSS or Atom feed, a sitemap or an
+    /// SVG image.
+    ///
+    /// Namespace prefixes used in the document can be used in selectors.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use nipper::Document;
+    ///
+    /// let xml = r#"<rss xmlns:atom="http://www.w3.org/2005/Atom"><channel>
+    ///     <atom:link href="https://example.com/feed" rel="self"/>
+    ///     <item><title>First</title></item>
+    /// </channel></rss>"#;
+    ///
+    /// let document = Document::from_xml(xml);
+    /// assert_eq!(document.select("item > title").text().to_string(), "First");
+    /// assert!(document.select("atom|link[rel=self]").exists());
+    /// ```
+    pub fn from_xml<T>(xml: T) -> Document
+    where
+        T: Into<StrTendril>,
+    {
+        xml5ever::driver::parse_document(Document::default(), Default::default()).one(xml)
+    }
+
     /// Return the underlying root document node.
     pub fn root(&self) -> NodeRef<NodeData> {
         self.tree.root()
@@ -77,7 +208,12 @@ impl TreeSink for Document {
 
     // Signal a parse error.
     fn parse_error(&mut self, msg: Cow<'static, str>) {
-        self.errors.push(msg);
+        self.errors.push(ParseError {
+            message: msg,
+            line: self.position.map_or(self.current_line, |p| p.line),
+            offset: self.position.map(|p| p.offset),
+            column: self.position.map(|p| p.column),
+        });
     }
 
     // Get a handle to the `Document` node.
@@ -100,6 +236,11 @@ impl TreeSink for Document {
     // Set the document's quirks mode.
     fn set_quirks_mode(&mut self, mode: QuirksMode) {
         self.quirks_mode = mode;
+        self.tree.set_quirks_mode(match mode {
+            tree_builder::Quirks => matching::QuirksMode::Quirks,
+            tree_builder::LimitedQuirks => matching::QuirksMode::LimitedQuirks,
+            tree_builder::NoQuirks => matching::QuirksMode::NoQuirks,
+        });
     }
 
     // Do two handles refer to the same node?.
@@ -126,6 +267,11 @@ impl TreeSink for Document {
         attrs: Vec<Attribute>,
         flags: ElementFlags,
     ) -> NodeId {
+        self.tree.add_namespace_of(&name);
+        for attr in attrs.iter() {
+            self.tree.add_namespace_of(&attr.name);
+        }
+
         let template_contents = if flags.template {
             Some(self.tree.create_node(NodeData::Document))
         } else {
@@ -140,6 +286,7 @@ impl TreeSink for Document {
         )));
 
         self.tree.set_name(id, name);
+        self.created = Some(id);
         id
     }
 
@@ -276,6 +423,11 @@ impl TreeSink for Document {
         self.tree.remove_from_parent(target);
     }
 
+    // Set the line the tokenizer is on, before each token is processed.
+    fn set_current_line(&mut self, line_number: u64) {
+        self.current_line = line_number;
+    }
+
     // Remove all the children from node and append them to new_parent.
     fn reparent_children(&mut self, node: &NodeId, new_parent: &NodeId) {
         self.tree.reparent_children_of(node, Some(*new_parent));
@@ -1,5 +1,6 @@
-use html5ever::serialize;
-use html5ever::serialize::SerializeOpts;
+use crate::index::{DocumentOrder, TreeIndex};
+use crate::position::SourcePosition;
+use crate::serializer::{SerializeOptions, XmlSerializer};
 use html5ever::LocalName;
 use markup5ever::serialize::TraversalScope;
 use markup5ever::serialize::TraversalScope::{ChildrenOnly, IncludeNode};
@@ -7,10 +8,14 @@ use markup5ever::serialize::{Serialize, Serializer};
 use markup5ever::Attribute;
 use markup5ever::QualName;
 use markup5ever::{namespace_url, ns};
+use markup5ever::{Namespace, Prefix};
+use selectors::matching::QuirksMode;
 use std::cell::RefCell;
+use std::cmp::Ordering;
 use std::collections::{HashMap, HashSet};
 use std::fmt::{self, Debug};
 use std::io;
+use std::rc::Rc;
 use tendril::StrTendril;
 
 /// Alias for `NodeRef`.
@@ -94,6 +99,14 @@ impl NodeId {
 pub struct Tree<T> {
     nodes: RefCell<Vec<InnerNode<T>>>,
     names: HashMap<NodeId, QualName>,
+    namespaces: HashMap<Prefix, Namespace>,
+
+    /// Built on demand and dropped whenever the tree changes.
+    index: RefCell<Option<Rc<TreeIndex>>>,
+    order: RefCell<Option<Rc<DocumentOrder>>>,
+
+    /// The quirks mode of the document, which selectors match in.
+    quirks_mode: QuirksMode,
 }
 
 impl<T: Debug> Debug for Tree<T> {
@@ -108,6 +121,10 @@ impl<T: Clone> Clone for Tree<T> {
             Self {
                 nodes: RefCell::new(nodes.clone()),
                 names: self.names.clone(),
+                namespaces: self.namespaces.clone(),
+                index: RefCell::new(self.index.borrow().clone()),
+                order: RefCell::new(self.order.borrow().clone()),
+                quirks_mode: self.quirks_mode,
             }
         })
     }
@@ -123,6 +140,10 @@ impl<T: Debug> Tree<T> {
         Self {
             nodes: RefCell::new(vec![InnerNode::new(root_id, root)]),
             names: HashMap::default(),
+            namespaces: HashMap::default(),
+            index: RefCell::new(None),
+            order: RefCell::new(None),
+            quirks_mode: QuirksMode::NoQuirks,
         }
     }
 
@@ -143,6 +164,29 @@ impl<T: Debug> Tree<T> {
         self.names.get(id).unwrap()
     }
 
+    /// Records the namespace bound to the prefix of `name`, if any. The first
+    /// binding of a prefix wins.
+    pub fn add_namespace_of(&mut self, name: &QualName) {
+        if let Some(ref prefix) = name.prefix {
+            if !self.namespaces.contains_key(prefix) {
+                self.namespaces.insert(prefix.clone(), name.ns.clone());
+            }
+        }
+    }
+
+    pub fn quirks_mode(&self) -> QuirksMode {
+        self.quirks_mode
+    }
+
+    pub fn set_quirks_mode(&mut self, mode: QuirksMode) {
+        self.quirks_mode = mode;
+    }
+
+    /// The namespace prefixes used by the names of elements and attributes in the tree.
+    pub fn namespaces(&self) -> &HashMap<Prefix, Namespace> {
+        &self.namespaces
+    }
+
     pub fn get(&self, id: &NodeId) -> Option<NodeRef<T>> {
         let nodes = self.nodes.borrow();
         let node = nodes.get(id.value).map(|_| NodeRef {
@@ -210,6 +254,7 @@ impl<T: Debug> Tree<T> {
     }
 
     pub fn append_child_data_of(&self, id: &NodeId, data: T) {
+        self.invalidate_index();
         with_cell_mut!(self.nodes, nodes, {
             let last_child_id = {
                 let parent = get_node_unchecked!(nodes, id);
@@ -237,6 +282,8 @@ impl<T: Debug> Tree<T> {
     }
 
     pub fn append_child_of(&self, id: &NodeId, new_child_id: &NodeId) {
+        self.remove_from_parent(new_child_id);
+
         with_cell_mut!(self.nodes, nodes, {
             let last_child_id = {
                 let parent = get_node_unchecked_mut!(nodes, id);
@@ -262,6 +309,7 @@ impl<T: Debug> Tree<T> {
     }
 
     pub fn append_children_from_another_tree(&self, id: &NodeId, tree: Tree<T>) {
+        self.invalidate_index();
         with_cell_mut!(self.nodes, nodes, {
             let mut new_nodes = tree.nodes.into_inner();
             assert!(
@@ -340,6 +388,7 @@ impl<T: Debug> Tree<T> {
     }
 
     pub fn append_prev_siblings_from_another_tree(&self, id: &NodeId, tree: Tree<T>) {
+        self.invalidate_index();
         with_cell_mut!(self.nodes, nodes, {
             let mut new_nodes = tree.nodes.into_inner();
             assert!(
@@ -428,6 +477,7 @@ impl<T: Debug> Tree<T> {
     }
 
     pub fn remove_from_parent(&self, id: &NodeId) {
+        self.invalidate_index();
         with_cell_mut!(self.nodes, nodes, {
             let node = get_node_unchecked_mut!(nodes, id);
             let parent_id = node.parent;
@@ -492,6 +542,7 @@ impl<T: Debug> Tree<T> {
     }
 
     pub fn reparent_children_of(&self, id: &NodeId, new_parent_id: Option<NodeId>) {
+        self.invalidate_index();
         with_cell_mut!(self.nodes, nodes, {
             let node = get_node_unchecked_mut!(nodes, id);
 
@@ -514,6 +565,47 @@ impl<T: Debug> Tree<T> {
         })
     }
 
+    fn invalidate_index(&self) {
+        self.index.borrow_mut().take();
+        self.order.borrow_mut().take();
+    }
+
+    /// The position of every node attached to the tree in document order,
+    /// computed if the tree changed since it was last asked for.
+    pub(crate) fn document_order(&self) -> Rc<DocumentOrder> {
+        if let Some(ref order) = *self.order.borrow() {
+            return order.clone();
+        }
+
+        let mut order = DocumentOrder::default();
+        with_cell!(self.nodes, nodes, {
+            let mut stack = vec![self.root_id()];
+            while let Some(id) = stack.pop() {
+                order.add(id);
+                stack.extend(children_of!(nodes, id).into_iter().rev());
+            }
+        });
+
+        let order = Rc::new(order);
+        *self.order.borrow_mut() = Some(order.clone());
+        order
+    }
+
+    /// The position of a node in document order, that is in a pre-order walk
+    /// of the tree, or `None` if the node is not attached to the tree. The
+    /// positions are computed once and kept until the tree changes.
+    pub fn document_position(&self, id: &NodeId) -> Option<usize> {
+        self.document_order().position(id)
+    }
+
+    /// Compares two nodes by document order. The nodes which are not
+    /// attached to the tree come after those which are, in the order they
+    /// were created.
+    pub fn compare_document_order(&self, a: &NodeId, b: &NodeId) -> Ordering {
+        let order = self.document_order();
+        document_order_key(&order, a).cmp(&document_order_key(&order, b))
+    }
+
     pub fn debug_nodes(&self) {
         with_cell!(self.nodes, nodes, {
             println!("==============");
@@ -543,6 +635,7 @@ impl<T: Debug> Tree<T> {
     where
         F: FnOnce(&mut InnerNode<T>) -> B,
     {
+        self.invalidate_index();
         let mut nodes = self.nodes.borrow_mut();
         let r = f(unsafe { nodes.get_unchecked_mut(id.value) });
         // self.nodes.set(nodes);
@@ -563,6 +656,54 @@ impl<T: Debug> Tree<T> {
     }
 }
 
+impl Tree<NodeData> {
+    /// The index of the elements attached to the tree, built if the tree
+    /// changed since it was last asked for.
+    pub(crate) fn index(&self) -> Rc<TreeIndex> {
+        if let Some(ref index) = *self.index.borrow() {
+            return index.clone();
+        }
+
+        let mut index = TreeIndex::default();
+        with_cell!(self.nodes, nodes, {
+            let mut stack = vec![self.root_id()];
+            while let Some(id) = stack.pop() {
+                let node = get_node_unchecked!(nodes, id);
+                if let NodeData::Element(ref e) = node.data {
+                    index.add(id, e);
+                }
+
+                stack.extend(children_of!(nodes, id).into_iter().rev());
+            }
+        });
+
+        let index = Rc::new(index);
+        *self.index.borrow_mut() = Some(index.clone());
+        index
+    }
+}
+
+/// Sorts `nodes` like `Tree::compare_document_order` does. Nodes of
+/// different trees are left in their order.
+pub(crate) fn sort_in_document_order<T: Debug>(nodes: &mut [NodeRef<T>]) {
+    let tree = match nodes.first() {
+        Some(node) => node.tree,
+        None => return,
+    };
+    if nodes.iter().any(|node| !std::ptr::eq(node.tree, tree)) {
+        return;
+    }
+
+    let order = tree.document_order();
+    nodes.sort_by_cached_key(|node| document_order_key(&order, &node.id));
+}
+
+/// A key sorting nodes in document order, and the detached ones last in the
+/// order they were created.
+fn document_order_key(order: &DocumentOrder, id: &NodeId) -> (usize, usize) {
+    (order.position(id).unwrap_or(usize::MAX), id.value)
+}
+
 pub struct InnerNode<T> {
     pub id: Option<NodeId>,
     pub parent: Option<NodeId>,
@@ -571,6 +712,9 @@ pub struct InnerNode<T> {
     pub first_child: Option<NodeId>,
     pub last_child: Option<NodeId>,
     pub data: T,
+
+    /// Where the node starts in the parsed source, if tracked.
+    pub position: Option<SourcePosition>,
 }
 
 impl<T> InnerNode<T> {
@@ -583,6 +727,7 @@ impl<T> InnerNode<T> {
             first_child: None,
             last_child: None,
             data,
+            position: None,
         }
     }
 }
@@ -597,6 +742,7 @@ impl<T: Debug> Debug for InnerNode<T> {
             .field("first_child", &self.first_child)
             .field("last_child", &self.last_child)
             .field("data", &self.data)
+            .field("position", &self.position)
             .finish()
     }
 }
@@ -634,6 +780,7 @@ impl<T: Clone> Clone for InnerNode<T> {
             first_child: self.first_child.clone(),
             last_child: self.last_child.clone(),
             data: self.data.clone(),
+            position: self.position,
         }
     }
 }
@@ -854,6 +1001,14 @@ impl<'a> Node<'a> {
         })
     }
 
+    /// Whether the element has an attribute named `name`.
+    pub fn has_attr(&self, name: &str) -> bool {
+        self.query(|node| match node.data {
+            NodeData::Element(ref e) => e.attrs.iter().any(|attr| &attr.name.local == name),
+            _ => false,
+        })
+    }
+
     pub fn attrs(&self) -> Vec<Attribute> {
         self.query(|node| match node.data {
             NodeData::Element(ref e) => e.attrs.iter().map(|attr| attr.clone()).collect(),
@@ -905,23 +1060,67 @@ impl<'a> Node<'a> {
     pub fn is_text(&self) -> bool {
         self.query(|node| node.is_text())
     }
+
+    /// Returns where the node starts in the parsed source. Positions are only
+    /// tracked when enabled with `ParseOptions::track_positions`.
+    pub fn source_position(&self) -> Option<SourcePosition> {
+        self.query(|node| node.position)
+    }
 }
 
 impl<'a> Node<'a> {
+    /// Serializes the node and its descendants. Is an alias for `outer_html`.
     pub fn html(&self) -> StrTendril {
+        self.outer_html()
+    }
+
+    /// Serializes the node and its descendants, like the DOM's `outerHTML`.
+    pub fn outer_html(&self) -> StrTendril {
+        SerializeOptions::default().serialize(self, TraversalScope::IncludeNode)
+    }
+
+    /// Serializes only the descendants of the node, like the DOM's `innerHTML`.
+    pub fn inner_html(&self) -> StrTendril {
+        let name = self.query(|node| match node.data {
+            NodeData::Element(ref e) => Some(e.name.clone()),
+            _ => None,
+        });
+
+        SerializeOptions::default().serialize(self, TraversalScope::ChildrenOnly(name))
+    }
+
+    /// Serializes the node as HTML, formatted according to `options`.
+    pub fn html_with(&self, options: &SerializeOptions) -> StrTendril {
+        options.serialize(self, TraversalScope::IncludeNode)
+    }
+
+    /// Serializes the node as well-formed XML, declaring the namespaces it
+    /// uses and closing empty elements with `/>`.
+    pub fn xml(&self) -> StrTendril {
+        self.serialize_xml(false)
+    }
+
+    /// Serializes the node as polyglot XHTML, which is well-formed XML that
+    /// HTML parsers read the same way: only void elements are self-closed,
+    /// like `<br />`, while other empty elements get an end tag. Scripts and
+    /// styles holding `<` or `&` are wrapped in a commented out CDATA
+    /// section, like `//<![CDATA[` and `//]]>`, instead of being escaped.
+    pub fn xhtml(&self) -> StrTendril {
+        self.serialize_xml(true)
+    }
+
+    fn serialize_xml(&self, xhtml: bool) -> StrTendril {
         let inner: SerializableNodeRef = self.clone().into();
 
         let mut result = vec![];
-        serialize(
-            &mut result,
-            &inner,
-            SerializeOpts {
-                scripting_enabled: true,
-                traversal_scope: TraversalScope::IncludeNode,
-                create_missing_parent: false,
-            },
-        )
-        .unwrap();
+        let mut serializer = XmlSerializer::new(&mut result, xhtml);
+        inner
+            .serialize_with(
+                &mut serializer,
+                TraversalScope::IncludeNode,
+                XmlSerializer::write_doctype_with_ids,
+            )
+            .unwrap();
         StrTendril::try_from_byte_slice(&result).unwrap()
     }
 
@@ -1033,6 +1232,26 @@ impl<'a> Serialize for SerializableNodeRef<'a> {
     fn serialize<S>(&self, serializer: &mut S, traversal_scope: TraversalScope) -> io::Result<()>
     where
         S: Serializer,
+    {
+        self.serialize_with(serializer, traversal_scope, |serializer, name, _, _| {
+            serializer.write_doctype(name)
+        })
+    }
+}
+
+impl<'a> SerializableNodeRef<'a> {
+    /// Works like `serialize` but writes doctypes with `write_doctype`, which
+    /// is given their name, public id and system id. `Serializer` only takes
+    /// the name, which is all HTML keeps.
+    pub(crate) fn serialize_with<S, D>(
+        &self,
+        serializer: &mut S,
+        traversal_scope: TraversalScope,
+        mut write_doctype: D,
+    ) -> io::Result<()>
+    where
+        S: Serializer,
+        D: FnMut(&mut S, &str, &str, &str) -> io::Result<()>,
     {
         let nodes = self.0.tree.nodes.borrow();
         let id = self.0.id;
@@ -1061,7 +1280,11 @@ impl<'a> Serialize for SerializableNodeRef<'a> {
 
                         Ok(())
                     }
-                    NodeData::Doctype { ref name, .. } => serializer.write_doctype(&name),
+                    NodeData::Doctype {
+                        ref name,
+                        ref public_id,
+                        ref system_id,
+                    } => write_doctype(serializer, name, public_id, system_id),
                     NodeData::Text { ref contents } => serializer.write_text(&contents),
                     NodeData::Comment { ref contents } => serializer.write_comment(&contents),
                     NodeData::ProcessingInstruction {
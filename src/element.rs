@@ -1,6 +1,6 @@
 use crate::dom_tree::{Node, NodeData};
-use crate::matcher::InnerSelector;
-use markup5ever::{namespace_url, ns};
+use crate::matcher::{InnerSelector, NonTSPseudoClass};
+use markup5ever::{namespace_url, ns, LocalName};
 use selectors::attr::AttrSelectorOperation;
 use selectors::attr::CaseSensitivity;
 use selectors::attr::NamespaceConstraint;
@@ -9,13 +9,15 @@ use selectors::matching::ElementSelectorFlags;
 use selectors::parser::SelectorImpl;
 use selectors::OpaqueElement;
 use std::ops::Deref;
+use tendril::StrTendril;
 
 impl<'a> selectors::Element for Node<'a> {
     type Impl = InnerSelector;
 
-    // Converts self into an opaque representation.
+    // Converts self into an opaque representation. It points into the arena,
+    // so that every `Node` of the same element converts to the same value.
     fn opaque(&self) -> OpaqueElement {
-        OpaqueElement::new(&self.id)
+        self.query(OpaqueElement::new)
     }
 
     fn parent_element(&self) -> Option<Self> {
@@ -112,14 +114,35 @@ impl<'a> selectors::Element for Node<'a> {
 
     fn match_non_ts_pseudo_class<F>(
         &self,
-        _pc: &<Self::Impl as SelectorImpl>::NonTSPseudoClass,
-        _context: &mut MatchingContext<Self::Impl>,
+        pc: &<Self::Impl as SelectorImpl>::NonTSPseudoClass,
+        context: &mut MatchingContext<Self::Impl>,
         _flags_setter: &mut F,
     ) -> bool
     where
         F: FnMut(&Self, ElementSelectorFlags),
     {
-        false
+        match pc {
+            // Applied to the matched set once matching is done.
+            NonTSPseudoClass::Position(_) => true,
+            NonTSPseudoClass::Has(selectors) => selectors
+                .iter()
+                .any(|selector| selector.matches(self, context.quirks_mode())),
+            NonTSPseudoClass::Contains(text) => self.text().contains(&**text),
+            NonTSPseudoClass::ContainsOwn(text) => own_text(self).contains(&**text),
+            #[cfg(feature = "regex")]
+            NonTSPseudoClass::Matches(pattern) => pattern.is_match(&self.text()),
+            #[cfg(feature = "regex")]
+            NonTSPseudoClass::MatchesOwn(pattern) => pattern.is_match(&own_text(self)),
+            NonTSPseudoClass::AnyLink | NonTSPseudoClass::Link => is_hyperlink(self),
+            NonTSPseudoClass::Checked => is_checked(self),
+            NonTSPseudoClass::Disabled => is_disabled(self),
+            NonTSPseudoClass::Enabled => is_enabled(self),
+            NonTSPseudoClass::Required => is_form_field(self) && self.has_attr("required"),
+            NonTSPseudoClass::Optional => is_form_field(self) && !self.has_attr("required"),
+            NonTSPseudoClass::ReadOnly => !is_read_write(self),
+            NonTSPseudoClass::ReadWrite => is_read_write(self),
+            NonTSPseudoClass::PlaceholderShown => is_placeholder_shown(self),
+        }
     }
 
     fn match_pseudo_element(
@@ -132,13 +155,7 @@ impl<'a> selectors::Element for Node<'a> {
 
     // Whether this element is a `link`.
     fn is_link(&self) -> bool {
-        self.query(|node| {
-            if let NodeData::Element(ref e) = node.data {
-                return &e.name.local == "link";
-            }
-
-            false
-        })
+        is_hyperlink(self)
     }
 
     // Whether the element is an HTML element.
@@ -216,3 +233,221 @@ impl<'a> selectors::Element for Node<'a> {
         self.is_document()
     }
 }
+
+/// Gets the text of the text nodes directly under the node.
+fn own_text(node: &Node) -> StrTendril {
+    let mut text = StrTendril::new();
+    for child in node.children() {
+        if child.is_text() {
+            text.push_tendril(&child.text());
+        }
+    }
+    text
+}
+
+/// Gets the local name of an HTML element.
+fn html_name(node: &Node) -> Option<LocalName> {
+    node.query(|node| match node.data {
+        NodeData::Element(ref e) if e.name.ns == ns!(html) => Some(e.name.local.clone()),
+        _ => None,
+    })
+}
+
+fn is_html_element(node: &Node, names: &[&str]) -> bool {
+    matches!(html_name(node), Some(name) if names.contains(&&*name))
+}
+
+/// Gets the lowercased `type` of an `<input>`, which defaults to `text`.
+fn input_type(node: &Node) -> String {
+    node.attr("type")
+        .map_or_else(|| "text".to_string(), |t| t.to_ascii_lowercase())
+}
+
+fn is_hyperlink(node: &Node) -> bool {
+    is_html_element(node, &["a", "area"]) && node.has_attr("href")
+}
+
+fn is_checked(node: &Node) -> bool {
+    match html_name(node).as_deref() {
+        Some("input") => {
+            matches!(&*input_type(node), "checkbox" | "radio") && node.has_attr("checked")
+        }
+        Some("option") => is_selected_option(node),
+        _ => false,
+    }
+}
+
+/// Whether the `<option>` is selected. A `<select>` showing a single option
+/// always has one selected, like in browsers: the last one with a `selected`
+/// attribute, or else the first one which is not disabled.
+fn is_selected_option(node: &Node) -> bool {
+    let select = match owner_select(node) {
+        Some(select) if !select.has_attr("multiple") && display_size(&select) <= 1 => select,
+        _ => return node.has_attr("selected"),
+    };
+
+    let options = options_of(&select);
+    let selected = options
+        .iter()
+        .rev()
+        .find(|option| option.has_attr("selected"))
+        .or_else(|| options.iter().find(|option| !is_disabled(option)));
+
+    matches!(selected, Some(selected) if selected.id == node.id)
+}
+
+/// Gets the `<select>` whose options the `<option>` is part of, its parent
+/// or the parent of its `<optgroup>`.
+fn owner_select<'a>(node: &Node<'a>) -> Option<Node<'a>> {
+    let mut parent = node.parent()?;
+    if is_html_element(&parent, &["optgroup"]) {
+        parent = parent.parent()?;
+    }
+
+    if is_html_element(&parent, &["select"]) {
+        Some(parent)
+    } else {
+        None
+    }
+}
+
+/// The number of options the `<select>` shows at once, given by its `size`.
+fn display_size(select: &Node) -> u32 {
+    select
+        .attr("size")
+        .and_then(|size| size.trim().parse().ok())
+        .filter(|&size| size > 0)
+        .unwrap_or(1)
+}
+
+/// Gets the options of the `<select>`, in document order.
+fn options_of<'a>(select: &Node<'a>) -> Vec<Node<'a>> {
+    let mut options = vec![];
+    for child in select.children() {
+        if is_html_element(&child, &["option"]) {
+            options.push(child);
+        } else if is_html_element(&child, &["optgroup"]) {
+            options.extend(
+                child
+                    .children()
+                    .into_iter()
+                    .filter(|c| is_html_element(c, &["option"])),
+            );
+        }
+    }
+    options
+}
+
+fn is_disabled(node: &Node) -> bool {
+    match html_name(node).as_deref() {
+        Some("button") | Some("input") | Some("select") | Some("textarea") | Some("fieldset") => {
+            node.has_attr("disabled") || in_disabled_fieldset(node)
+        }
+        Some("optgroup") => node.has_attr("disabled"),
+        Some("option") => {
+            node.has_attr("disabled")
+                || matches!(node.parent(), Some(parent)
+                    if is_html_element(&parent, &["optgroup"]) && parent.has_attr("disabled"))
+        }
+        _ => false,
+    }
+}
+
+/// Whether the node is inside a disabled `<fieldset>`, but not inside its
+/// first `<legend>`.
+fn in_disabled_fieldset(node: &Node) -> bool {
+    let mut child = node.clone();
+
+    while let Some(parent) = child.parent() {
+        if is_html_element(&parent, &["fieldset"]) && parent.has_attr("disabled") {
+            let legend = parent
+                .children()
+                .into_iter()
+                .find(|c| is_html_element(c, &["legend"]));
+
+            if !matches!(legend, Some(legend) if legend.id == child.id) {
+                return true;
+            }
+        }
+
+        child = parent;
+    }
+
+    false
+}
+
+fn is_enabled(node: &Node) -> bool {
+    is_html_element(
+        node,
+        &[
+            "button", "input", "select", "textarea", "fieldset", "optgroup", "option",
+        ],
+    ) && !is_disabled(node)
+}
+
+fn is_form_field(node: &Node) -> bool {
+    is_html_element(node, &["input", "select", "textarea"])
+}
+
+fn is_read_write(node: &Node) -> bool {
+    match html_name(node).as_deref() {
+        Some("input") => {
+            matches!(
+                &*input_type(node),
+                "text"
+                    | "search"
+                    | "url"
+                    | "tel"
+                    | "email"
+                    | "password"
+                    | "date"
+                    | "month"
+                    | "week"
+                    | "time"
+                    | "datetime-local"
+                    | "number"
+            ) && !node.has_attr("readonly")
+                && !is_disabled(node)
+        }
+        Some("textarea") => !node.has_attr("readonly") && !is_disabled(node),
+        Some(_) => is_editable(node),
+        None => false,
+    }
+}
+
+/// Whether the node is editable through `contenteditable` on itself or on
+/// its nearest ancestor with a valid value.
+fn is_editable(node: &Node) -> bool {
+    let mut current = Some(node.clone());
+
+    while let Some(node) = current {
+        if let Some(value) = node.attr("contenteditable") {
+            match &*value.to_ascii_lowercase() {
+                "" | "true" | "plaintext-only" => return true,
+                "false" => return false,
+                _ => (),
+            }
+        }
+
+        current = node.parent();
+    }
+
+    false
+}
+
+fn is_placeholder_shown(node: &Node) -> bool {
+    if !node.has_attr("placeholder") {
+        return false;
+    }
+
+    match html_name(node).as_deref() {
+        Some("input") => {
+            matches!(
+                &*input_type(node),
+                "text" | "search" | "url" | "tel" | "email" | "password" | "number"
+            ) && !matches!(node.attr("value"), Some(value) if !value.is_empty())
+        }
+        Some("textarea") => node.text().is_empty(),
+        _ => false,
+    }
+}
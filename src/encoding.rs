@@ -0,0 +1,297 @@
+//! Character encoding detection for byte input, following the [encoding sniffing algorithm]
+//! of the HTML standard.
+//!
+//! [encoding sniffing algorithm]: https://html.spec.whatwg.org/multipage/parsing.html#encoding-sniffing-algorithm
+
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1252, X_USER_DEFINED};
+
+/// The number of bytes the prescan looks at.
+pub(crate) const PRESCAN_LIMIT: usize = 1024;
+
+/// A lowercased attribute name and value.
+type Attr = (Vec<u8>, Vec<u8>);
+
+/// Determines the character encoding of `bytes`.
+///
+/// The byte order mark wins, then the transport layer hint (either a bare
+/// charset label or a whole `Content-Type` value), then a `<meta>`
+/// declaration found in the first 1024 bytes. Without any of those, the
+/// encoding is guessed, see `guess`.
+pub(crate) fn sniff(bytes: &[u8], transport_charset: Option<&str>) -> &'static Encoding {
+    sniff_declared(bytes, transport_charset)
+        .or_else(|| guess(bytes, true))
+        .unwrap_or(UTF_8)
+}
+
+/// Determines the character encoding of `bytes` from the byte order mark, the
+/// transport layer hint or a `<meta>` declaration, like `sniff`.
+pub(crate) fn sniff_declared(
+    bytes: &[u8],
+    transport_charset: Option<&str>,
+) -> Option<&'static Encoding> {
+    sniff_bom_or_transport(bytes, transport_charset, true)
+        .or_else(|| prescan(&bytes[..bytes.len().min(PRESCAN_LIMIT)]))
+}
+
+/// Determines the character encoding of `bytes` from the byte order mark or
+/// the transport layer hint, which need no prescan. Returns `None` if `bytes`
+/// may still start a byte order mark, `last` telling whether it is all of the
+/// input.
+pub(crate) fn sniff_bom_or_transport(
+    bytes: &[u8],
+    transport_charset: Option<&str>,
+    last: bool,
+) -> Option<&'static Encoding> {
+    if let Some((encoding, _)) = Encoding::for_bom(bytes) {
+        return Some(encoding);
+    }
+
+    let boms: [&[u8]; 3] = [b"\xEF\xBB\xBF", b"\xFE\xFF", b"\xFF\xFE"];
+    if !last
+        && boms
+            .iter()
+            .any(|bom| bom.len() > bytes.len() && bom.starts_with(bytes))
+    {
+        return None;
+    }
+
+    transport_charset.and_then(transport_encoding)
+}
+
+/// Guesses between UTF-8 and windows-1252 from the 1024 bytes starting at the
+/// first non-ASCII one, which are UTF-8 if they are valid UTF-8. A character
+/// cut off at the end of them does not count as invalid.
+///
+/// Returns `None` if more input is needed, `last` telling whether `bytes` is
+/// all of it. ASCII reads the same in both encodings, so a streaming parser
+/// can parse it before the encoding is known.
+pub(crate) fn guess(bytes: &[u8], last: bool) -> Option<&'static Encoding> {
+    let start = match bytes.iter().position(|b| !b.is_ascii()) {
+        Some(start) => start,
+        None if last => return Some(UTF_8),
+        None => return None,
+    };
+
+    let bytes = &bytes[start..];
+    if bytes.len() < PRESCAN_LIMIT && !last {
+        return None;
+    }
+
+    match std::str::from_utf8(&bytes[..bytes.len().min(PRESCAN_LIMIT)]) {
+        Ok(_) => Some(UTF_8),
+        Err(e) if e.error_len().is_none() => Some(UTF_8),
+        Err(_) => Some(WINDOWS_1252),
+    }
+}
+
+fn transport_encoding(hint: &str) -> Option<&'static Encoding> {
+    let hint = hint.as_bytes();
+    extract_charset(hint)
+        .and_then(Encoding::for_label)
+        .or_else(|| Encoding::for_label(hint))
+}
+
+/// Prescans a byte stream to determine its encoding.
+/// See <https://html.spec.whatwg.org/multipage/parsing.html#prescan-a-byte-stream-to-determine-its-encoding>
+fn prescan(bytes: &[u8]) -> Option<&'static Encoding> {
+    let mut pos = 0;
+
+    while pos < bytes.len() {
+        let rest = &bytes[pos..];
+
+        if rest.starts_with(b"<!--") {
+            pos += 2 + find(&rest[2..], b"-->")? + 3;
+        } else if starts_with_ignore_case(rest, b"<meta")
+            && matches!(rest.get(5), Some(&b) if is_space(b) || b == b'/')
+        {
+            pos += 5;
+            if let Some(encoding) = prescan_meta(bytes, &mut pos)? {
+                return Some(encoding);
+            }
+        } else if rest[0] == b'<'
+            && (matches!(rest.get(1), Some(b) if b.is_ascii_alphabetic())
+                || (rest.get(1) == Some(&b'/')
+                    && matches!(rest.get(2), Some(b) if b.is_ascii_alphabetic())))
+        {
+            pos += rest
+                .iter()
+                .position(|&b| is_space(b) || b == b'>')
+                .unwrap_or(rest.len());
+
+            while get_attribute(bytes, &mut pos)?.is_some() {}
+        } else if rest.starts_with(b"<!") || rest.starts_with(b"</") || rest.starts_with(b"<?") {
+            pos += rest.iter().position(|&b| b == b'>')? + 1;
+        } else {
+            pos += 1;
+        }
+    }
+
+    None
+}
+
+/// Processes the attributes of a `<meta>` tag. The outer `None` means that the
+/// input ended in the middle of the tag.
+fn prescan_meta(bytes: &[u8], pos: &mut usize) -> Option<Option<&'static Encoding>> {
+    let mut seen: Vec<Vec<u8>> = vec![];
+    let mut got_pragma = false;
+    let mut need_pragma = None;
+    let mut charset = None;
+
+    while let Some((name, value)) = get_attribute(bytes, pos)? {
+        if seen.contains(&name) {
+            continue;
+        }
+
+        match &name[..] {
+            b"http-equiv" => got_pragma = got_pragma || value == b"content-type",
+            b"content" if charset.is_none() => {
+                if let Some(label) = extract_charset(&value) {
+                    charset = Encoding::for_label(label);
+                    need_pragma = Some(true);
+                }
+            }
+            b"charset" => {
+                charset = Encoding::for_label(&value);
+                need_pragma = Some(false);
+            }
+            _ => (),
+        }
+
+        seen.push(name);
+    }
+
+    let encoding = match need_pragma {
+        None => None,
+        Some(true) if !got_pragma => None,
+        _ => charset,
+    };
+
+    Some(encoding.map(|encoding| {
+        if encoding == UTF_16BE || encoding == UTF_16LE {
+            UTF_8
+        } else if encoding == X_USER_DEFINED {
+            WINDOWS_1252
+        } else {
+            encoding
+        }
+    }))
+}
+
+/// Gets an attribute from the tag at `pos`, with the name and the value
+/// lowercased. The outer `None` means that the input ended before the
+/// attribute did, the inner one that the tag has no more attributes.
+/// See <https://html.spec.whatwg.org/multipage/parsing.html#concept-get-attributes-when-sniffing>
+fn get_attribute(bytes: &[u8], pos: &mut usize) -> Option<Option<Attr>> {
+    while is_space(*bytes.get(*pos)?) || bytes[*pos] == b'/' {
+        *pos += 1;
+    }
+
+    if bytes[*pos] == b'>' {
+        return Some(None);
+    }
+
+    let mut name = vec![];
+    let mut value = vec![];
+
+    loop {
+        match *bytes.get(*pos)? {
+            b'=' if !name.is_empty() => break,
+            b if is_space(b) => {
+                while is_space(*bytes.get(*pos)?) {
+                    *pos += 1;
+                }
+
+                if bytes[*pos] != b'=' {
+                    return Some(Some((name, value)));
+                }
+                break;
+            }
+            b'/' | b'>' => return Some(Some((name, value))),
+            b => name.push(b.to_ascii_lowercase()),
+        }
+        *pos += 1;
+    }
+
+    // Skips the '='.
+    *pos += 1;
+    while is_space(*bytes.get(*pos)?) {
+        *pos += 1;
+    }
+
+    match bytes[*pos] {
+        quote @ b'"' | quote @ b'\'' => loop {
+            *pos += 1;
+            match *bytes.get(*pos)? {
+                b if b == quote => {
+                    *pos += 1;
+                    return Some(Some((name, value)));
+                }
+                b => value.push(b.to_ascii_lowercase()),
+            }
+        },
+        b'>' => Some(Some((name, value))),
+        _ => loop {
+            match *bytes.get(*pos)? {
+                b if is_space(b) || b == b'>' => return Some(Some((name, value))),
+                b => value.push(b.to_ascii_lowercase()),
+            }
+            *pos += 1;
+        },
+    }
+}
+
+/// Extracts the charset label from a `content` attribute or a `Content-Type` value.
+/// See <https://html.spec.whatwg.org/multipage/urls-and-fetching.html#algorithm-for-extracting-a-character-encoding-from-a-meta-element>
+fn extract_charset(content: &[u8]) -> Option<&[u8]> {
+    let mut pos = 0;
+
+    loop {
+        pos += find_ignore_case(&content[pos..], b"charset")? + b"charset".len();
+
+        while matches!(content.get(pos), Some(&b) if is_space(b)) {
+            pos += 1;
+        }
+
+        if content.get(pos) == Some(&b'=') {
+            break;
+        }
+    }
+
+    pos += 1;
+    while matches!(content.get(pos), Some(&b) if is_space(b)) {
+        pos += 1;
+    }
+
+    let rest = &content[pos..];
+    match *rest.first()? {
+        quote @ b'"' | quote @ b'\'' => {
+            let end = rest[1..].iter().position(|&b| b == quote)?;
+            Some(&rest[1..end + 1])
+        }
+        _ => {
+            let end = rest
+                .iter()
+                .position(|&b| is_space(b) || b == b';')
+                .unwrap_or(rest.len());
+            Some(&rest[..end])
+        }
+    }
+}
+
+fn is_space(b: u8) -> bool {
+    matches!(b, b'\t' | b'\n' | b'\x0C' | b'\r' | b' ')
+}
+
+fn starts_with_ignore_case(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.len() >= needle.len() && haystack[..needle.len()].eq_ignore_ascii_case(needle)
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+fn find_ignore_case(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|w| w.eq_ignore_ascii_case(needle))
+}
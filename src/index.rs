@@ -0,0 +1,87 @@
+//! Indexes from ids, classes and local names to the elements of a tree, so
+//! that selectors like `#main`, `.item` or `a` look up their candidates
+//! instead of walking the whole tree.
+//!
+//! The position of every node in document order is kept apart, so that
+//! sorting nodes does not build the selector index.
+//!
+//! A tree builds each the first time it is asked for it and drops them on
+//! any mutation.
+
+use crate::dom_tree::{Element, NodeId};
+use html5ever::LocalName;
+use std::collections::HashMap;
+
+/// What the rightmost compound selector of a selector requires of the
+/// elements it matches.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum IndexKey {
+    Id(LocalName),
+    Class(LocalName),
+    LocalName(LocalName),
+}
+
+/// The elements attached to a tree, by id, class and local name, each list
+/// in document order.
+#[derive(Debug, Default)]
+pub(crate) struct TreeIndex {
+    ids: HashMap<String, Vec<NodeId>>,
+    classes: HashMap<String, Vec<NodeId>>,
+    names: HashMap<LocalName, Vec<NodeId>>,
+}
+
+impl TreeIndex {
+    /// Adds the next element in document order.
+    pub(crate) fn add(&mut self, id: NodeId, element: &Element) {
+        self.names
+            .entry(element.name.local.clone())
+            .or_default()
+            .push(id);
+
+        for attr in &element.attrs {
+            match &*attr.name.local {
+                "id" => self.ids.entry(attr.value.to_string()).or_default().push(id),
+                "class" => {
+                    for class in attr.value.split_whitespace() {
+                        let ids = self.classes.entry(class.to_string()).or_default();
+                        // `class="a a"` lists the element once.
+                        if ids.last() != Some(&id) {
+                            ids.push(id);
+                        }
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+
+    /// The elements with `key`, in document order.
+    pub(crate) fn get(&self, key: &IndexKey) -> &[NodeId] {
+        let ids = match key {
+            IndexKey::Id(id) => self.ids.get(&**id),
+            IndexKey::Class(class) => self.classes.get(&**class),
+            IndexKey::LocalName(name) => self.names.get(name),
+        };
+
+        ids.map_or(&[], |ids| &ids[..])
+    }
+}
+
+/// The position of every node attached to a tree in a pre-order walk.
+#[derive(Debug, Default)]
+pub(crate) struct DocumentOrder {
+    positions: HashMap<NodeId, usize>,
+}
+
+impl DocumentOrder {
+    /// Adds the next node in document order.
+    pub(crate) fn add(&mut self, id: NodeId) {
+        self.positions.insert(id, self.positions.len());
+    }
+
+    /// The position of `id` in document order, or `None` if the node is not
+    /// attached to the tree.
+    pub(crate) fn position(&self, id: &NodeId) -> Option<usize> {
+        self.positions.get(id).copied()
+    }
+}
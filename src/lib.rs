@@ -8,6 +8,12 @@
 //! * Query
 //! * Traversal
 //!
+//! # Cargo features
+//!
+//! * `async`: parsing from `futures::io::AsyncRead` with `Document::from_async_reader`.
+//! * `regex`: the `:matches()` and `:matchesOwn()` pseudo-classes, which match
+//!   the text of elements against a regular expression.
+//!
 //! # Get started
 //!
 //! ```
@@ -30,11 +36,18 @@
 mod document;
 mod dom_tree;
 mod element;
+mod encoding;
+mod index;
 mod manipulation;
 mod matcher;
+mod matcher_set;
+mod parser;
+mod position;
+mod positional;
 mod property;
 mod query;
 mod selection;
+mod serializer;
 mod traversal;
 
 pub use document::Document;
@@ -44,6 +57,10 @@ pub use dom_tree::NodeId;
 pub use dom_tree::NodeRef;
 #[doc(hidden)]
 pub use dom_tree::SerializableNodeRef;
-pub use matcher::Matcher;
+pub use matcher::{Matcher, Namespaces, SelectorError, Specificity};
+pub use matcher_set::MatcherSet;
+pub use parser::{DocumentParser, ParseOptions};
+pub use position::{ParseError, SourcePosition};
 pub use selection::Selection;
+pub use serializer::SerializeOptions;
 pub use traversal::Selections;
@@ -1,34 +1,11 @@
-use crate::Document;
+use crate::parser::ParseOptions;
 use crate::Selection;
-use html5ever::QualName;
-use html5ever::{
-    tree_builder::{NoQuirks, TreeBuilderOpts},
-    ParseOpts,
-};
-use markup5ever::local_name;
-use markup5ever::{namespace_url, ns};
 use tendril::StrTendril;
 use tendril::TendrilSink;
 
 macro_rules! parse_html {
     ($html: expr) => {
-        html5ever::parse_fragment(
-            Document::default(),
-            ParseOpts {
-                tokenizer: Default::default(),
-                tree_builder: TreeBuilderOpts {
-                    exact_errors: false,
-                    scripting_enabled: true,
-                    iframe_srcdoc: false,
-                    drop_doctype: true,
-                    ignore_missing_rules: false,
-                    quirks_mode: NoQuirks,
-                },
-            },
-            QualName::new(None, ns!(html), local_name!("")),
-            Vec::new(),
-        )
-        .one($html)
+        ParseOptions::default().fragment_parser("").one($html)
     };
 }
 
@@ -1,11 +1,18 @@
-use crate::dom_tree::{NodeData, NodeId, NodeRef};
-use cssparser::ParseError;
-use html5ever::{LocalName, Namespace};
+use crate::dom_tree::{sort_in_document_order, NodeData, NodeId, NodeRef};
+use crate::index::IndexKey;
+use crate::positional;
+use cssparser::{BasicParseErrorKind, ParseError, ParseErrorKind, SourceLocation};
+use cssparser::{CowRcStr, Delimiter, ToCss};
+use html5ever::{namespace_url, ns, LocalName, Namespace, Prefix};
+#[cfg(feature = "regex")]
+use regex::Regex;
 use selectors::matching;
-use selectors::parser::{self, SelectorList, SelectorParseErrorKind};
+use selectors::matching::QuirksMode;
+use selectors::parser::{self, AncestorHashes, Component, SelectorList, SelectorParseErrorKind};
 use selectors::visitor;
 use selectors::Element;
-use std::collections::HashSet;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 
 /// CSS selector.
@@ -16,35 +23,475 @@ pub struct Matcher {
 
 impl Matcher {
     /// Greate a new CSS matcher.
-    pub fn new(sel: &str) -> Result<Self, ParseError<SelectorParseErrorKind>> {
+    pub fn new(sel: &str) -> Result<Self, SelectorError> {
+        Self::with_namespaces(sel, &HashMap::new())
+    }
+
+    /// Creates a CSS matcher which resolves namespace prefixes like `svg|rect`
+    /// with `namespaces`.
+    pub(crate) fn with_namespaces(
+        sel: &str,
+        namespaces: &HashMap<Prefix, Namespace>,
+    ) -> Result<Self, SelectorError> {
+        Self::parse(sel, &InnerSelectorParser::new(namespaces))
+    }
+
+    /// Creates a CSS matcher which resolves namespace prefixes like
+    /// `svg|circle` or `[xlink|href]` with `namespaces`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use nipper::{Document, Matcher, Namespaces};
+    ///
+    /// let document = Document::from(
+    ///     r#"<svg><a xlink:href="/a"><circle r="1"/></a></svg><a href="/b">B</a>"#,
+    /// );
+    ///
+    /// let matcher = Matcher::with_prefixes("svg|a[xlink|href]", &Namespaces::web()).unwrap();
+    /// assert_eq!(document.select_matcher(&matcher).length(), 1);
+    ///
+    /// let matcher = Matcher::with_prefixes("html|a[href]", &Namespaces::web()).unwrap();
+    /// assert_eq!(document.select_matcher(&matcher).text().to_string(), "B");
+    /// ```
+    pub fn with_prefixes(sel: &str, namespaces: &Namespaces) -> Result<Self, SelectorError> {
+        Self::parse(
+            sel,
+            &InnerSelectorParser {
+                default_namespace: namespaces.default.as_ref(),
+                ..InnerSelectorParser::new(&namespaces.prefixes)
+            },
+        )
+    }
+
+    /// Works like `with_namespaces` but also accepts the positional
+    /// pseudo-classes, which only the `positional` module can evaluate.
+    pub(crate) fn with_positional(
+        sel: &str,
+        namespaces: &HashMap<Prefix, Namespace>,
+    ) -> Result<Self, SelectorError> {
+        Self::parse(
+            sel,
+            &InnerSelectorParser {
+                positional: true,
+                ..InnerSelectorParser::new(namespaces)
+            },
+        )
+    }
+
+    fn parse(sel: &str, parser: &InnerSelectorParser) -> Result<Self, SelectorError> {
         let mut input = cssparser::ParserInput::new(sel);
-        let mut parser = cssparser::Parser::new(&mut input);
-        selectors::parser::SelectorList::parse(&InnerSelectorParser, &mut parser)
+        let mut input = cssparser::Parser::new(&mut input);
+        SelectorList::parse(parser, &mut input)
             .map(|selector_list| Matcher { selector_list })
+            .map_err(|e| SelectorError::new(sel, e))
     }
 
-    pub(crate) fn match_element<E>(&self, element: &E) -> bool
-    where
-        E: Element<Impl = InnerSelector>,
-    {
+    /// Whether `node` matches, in the quirks mode of its document.
+    pub(crate) fn match_element(&self, node: &NodeRef<NodeData>) -> bool {
         let mut ctx = matching::MatchingContext::new(
             matching::MatchingMode::Normal,
             None,
             None,
-            matching::QuirksMode::NoQuirks,
+            node.tree.quirks_mode(),
         );
 
-        matching::matches_selector_list(&self.selector_list, element, &mut ctx)
+        matching::matches_selector_list(&self.selector_list, node, &mut ctx)
+    }
+
+    /// The specificity of each of the comma separated selectors. Like in
+    /// browsers, `:has()` counts as its most specific selector.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use nipper::{Matcher, Specificity};
+    ///
+    /// let matcher = Matcher::new("#main a.external, li").unwrap();
+    /// assert_eq!(
+    ///     matcher.specificity(),
+    ///     vec![Specificity::new(1, 1, 1), Specificity::new(0, 0, 1)]
+    /// );
+    /// ```
+    pub fn specificity(&self) -> Vec<Specificity> {
+        self.selector_list.0.iter().map(specificity_of).collect()
+    }
+
+    /// The indexes of the comma separated selectors which match `node`.
+    pub fn matching_selectors(&self, node: &NodeRef<NodeData>) -> Vec<usize> {
+        let mut ctx = matching::MatchingContext::new(
+            matching::MatchingMode::Normal,
+            None,
+            None,
+            node.tree.quirks_mode(),
+        );
+
+        self.selector_list
+            .0
+            .iter()
+            .enumerate()
+            .filter(|(_, selector)| {
+                matching::matches_selector(selector, 0, None, node, &mut ctx, &mut |_, _| {})
+            })
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Serializes the selectors back to CSS. Parsing the result gives an
+    /// equivalent matcher.
+    pub fn to_css(&self) -> String {
+        self.selector_list.to_css_string()
+    }
+
+    /// The hashes of what each selector requires of the ancestors of the
+    /// elements it matches, to reject elements with a bloom filter. In quirks
+    /// mode, ids and classes are left out as they match case-insensitively.
+    pub(crate) fn ancestor_hashes(&self, quirks_mode: QuirksMode) -> Vec<AncestorHashes> {
+        self.selector_list
+            .0
+            .iter()
+            .map(|selector| AncestorHashes::new(selector, quirks_mode))
+            .collect()
+    }
+
+    /// Works like `match_element` but with `ctx`, whose bloom filter, if any,
+    /// holds the ancestors of `element`. `hashes` are the `ancestor_hashes`.
+    pub(crate) fn match_element_with<E>(
+        &self,
+        element: &E,
+        hashes: &[AncestorHashes],
+        ctx: &mut matching::MatchingContext<InnerSelector>,
+    ) -> bool
+    where
+        E: Element<Impl = InnerSelector>,
+    {
+        self.selector_list
+            .0
+            .iter()
+            .zip(hashes)
+            .any(|(selector, hashes)| {
+                matching::matches_selector(selector, 0, Some(hashes), element, ctx, &mut |_, _| {})
+            })
+    }
+
+    /// The index keys the elements matching any of the selectors have, or
+    /// `None` if some selector requires neither an id, a class nor a local
+    /// name of the elements it matches. In quirks mode, ids and classes are
+    /// not keys as they match case-insensitively.
+    pub(crate) fn index_keys(&self, quirks_mode: QuirksMode) -> Option<Vec<IndexKey>> {
+        let quirks = quirks_mode == QuirksMode::Quirks;
+        let mut keys = vec![];
+
+        for selector in self.selector_list.0.iter() {
+            let mut id = None;
+            let mut class = None;
+            let mut names = None;
+
+            // The rightmost compound selector.
+            for component in selector.iter() {
+                match component {
+                    Component::ID(name) if !quirks => id = Some(IndexKey::Id(name.clone())),
+                    Component::Class(name) if !quirks => {
+                        class = Some(IndexKey::Class(name.clone()))
+                    }
+                    Component::LocalName(name) => {
+                        names = Some((name.name.clone(), name.lower_name.clone()))
+                    }
+                    _ => (),
+                }
+            }
+
+            match (id, class, names) {
+                (Some(key), _, _) | (None, Some(key), _) => keys.push(key),
+                (None, None, Some((name, lower_name))) => {
+                    if name != lower_name {
+                        keys.push(IndexKey::LocalName(lower_name));
+                    }
+                    keys.push(IndexKey::LocalName(name));
+                }
+                (None, None, None) => return None,
+            }
+        }
+
+        Some(keys)
+    }
+
+    /// Whether a selector uses `:scope` outside of functional pseudo-classes.
+    fn uses_scope(&self) -> bool {
+        self.selector_list.0.iter().any(|selector| {
+            selector
+                .iter_raw_match_order()
+                .any(|component| matches!(component, Component::Scope))
+        })
+    }
+}
+
+/// Namespace prefixes for selectors, given to `Matcher::with_prefixes`.
+#[derive(Debug, Clone, Default)]
+pub struct Namespaces {
+    prefixes: HashMap<Prefix, Namespace>,
+    default: Option<Namespace>,
+}
+
+impl Namespaces {
+    /// Creates an empty set of prefixes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The `html`, `svg`, `mathml` and `xlink` prefixes, bound to the
+    /// namespaces the HTML parser puts elements and attributes in.
+    pub fn web() -> Self {
+        Self::new().html().svg().mathml().xlink()
+    }
+
+    /// Binds `html` to the HTML namespace.
+    pub fn html(self) -> Self {
+        self.prefix("html", &ns!(html))
+    }
+
+    /// Binds `svg` to the SVG namespace.
+    pub fn svg(self) -> Self {
+        self.prefix("svg", &ns!(svg))
+    }
+
+    /// Binds `mathml` to the MathML namespace.
+    pub fn mathml(self) -> Self {
+        self.prefix("mathml", &ns!(mathml))
+    }
+
+    /// Binds `xlink` to the XLink namespace, the one of attributes like
+    /// `xlink:href` in SVG.
+    pub fn xlink(self) -> Self {
+        self.prefix("xlink", &ns!(xlink))
+    }
+
+    /// Binds `prefix` to the namespace `url`.
+    pub fn prefix(mut self, prefix: &str, url: &str) -> Self {
+        self.prefixes
+            .insert(Prefix::from(prefix), Namespace::from(url));
+        self
+    }
+
+    /// Sets the namespace of type selectors without a prefix, so that `circle`
+    /// only matches elements in that namespace. Attribute selectors without a
+    /// prefix are not affected.
+    pub fn default_namespace(mut self, url: &str) -> Self {
+        self.default = Some(Namespace::from(url));
+        self
     }
 }
 
+/// The specificity of a selector, which decides between conflicting rules:
+/// the higher one wins. They compare by the number of id selectors, then of
+/// class, attribute and pseudo-class selectors, then of type and
+/// pseudo-element selectors.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Specificity {
+    pub ids: u32,
+    pub classes: u32,
+    pub elements: u32,
+}
+
+impl Specificity {
+    pub fn new(ids: u32, classes: u32, elements: u32) -> Self {
+        Specificity {
+            ids,
+            classes,
+            elements,
+        }
+    }
+
+    /// Unpacks the specificity the way `selectors` packs it, with 10 bits
+    /// for each count.
+    fn from_packed(specificity: u32) -> Self {
+        const MAX: u32 = (1 << 10) - 1;
+        Specificity::new(
+            specificity >> 20,
+            (specificity >> 10) & MAX,
+            specificity & MAX,
+        )
+    }
+}
+
+/// The specificity of `selector`. Like in browsers, `:has()` counts as its
+/// most specific relative selector, where `selectors` counts it as a
+/// pseudo-class.
+fn specificity_of(selector: &parser::Selector<InnerSelector>) -> Specificity {
+    let mut specificity = Specificity::from_packed(selector.specificity());
+    add_has_specificity(selector.iter_raw_match_order(), &mut specificity);
+    specificity
+}
+
+fn add_has_specificity<'a>(
+    components: impl Iterator<Item = &'a Component<InnerSelector>>,
+    specificity: &mut Specificity,
+) {
+    for component in components {
+        match component {
+            Component::NonTSPseudoClass(NonTSPseudoClass::Has(selectors)) => {
+                let most = selectors
+                    .iter()
+                    .map(RelativeSelector::specificity)
+                    .max()
+                    .unwrap_or_default();
+                specificity.ids += most.ids;
+                specificity.classes = specificity.classes + most.classes - 1;
+                specificity.elements += most.elements;
+            }
+            Component::Negation(negated) => add_has_specificity(negated.iter(), specificity),
+            _ => (),
+        }
+    }
+}
+
+/// An error that occurred while parsing a CSS selector.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelectorError {
+    /// What went wrong.
+    pub message: Cow<'static, str>,
+
+    /// The offending token, if the error is about one.
+    pub token: Option<String>,
+
+    /// Where the error occurred in the selector, in characters starting at 1.
+    pub column: usize,
+}
+
+impl SelectorError {
+    fn new(sel: &str, e: ParseError<InnerParseErrorKind>) -> Self {
+        use SelectorParseErrorKind::*;
+
+        if let ParseErrorKind::Custom(InnerParseErrorKind::Relative(ref inner)) = e.kind {
+            // The relative selector was parsed on its own, prefixed by `SCOPE_PREFIX`.
+            return SelectorError {
+                column: char_column(sel, e.location)
+                    + inner.column.saturating_sub(SCOPE_PREFIX.len() + 1),
+                ..(**inner).clone()
+            };
+        }
+
+        let (message, token): (&'static str, Option<String>) = match e.kind {
+            ParseErrorKind::Basic(BasicParseErrorKind::UnexpectedToken(ref t)) => {
+                ("unexpected token", Some(t.to_css_string()))
+            }
+            ParseErrorKind::Basic(BasicParseErrorKind::EndOfInput) => {
+                ("unexpected end of selector", None)
+            }
+            ParseErrorKind::Basic(_) => ("invalid selector", None),
+            #[cfg(feature = "regex")]
+            ParseErrorKind::Custom(InnerParseErrorKind::InvalidRegex(ref pattern)) => {
+                ("invalid regular expression", Some(pattern.clone()))
+            }
+            ParseErrorKind::Custom(InnerParseErrorKind::Relative(_)) => unreachable!(),
+            ParseErrorKind::Custom(InnerParseErrorKind::Selector(ref kind)) => match kind {
+                PseudoElementInComplexSelector => ("pseudo-element in a complex selector", None),
+                NoQualifiedNameInAttributeSelector(t) => {
+                    ("expected an attribute name", Some(t.to_css_string()))
+                }
+                EmptySelector => ("empty selector", None),
+                DanglingCombinator => ("dangling combinator", None),
+                NonSimpleSelectorInNegation => ("non-simple selector in :not()", None),
+                NonCompoundSelector => ("expected a compound selector", None),
+                NonPseudoElementAfterSlotted | InvalidPseudoElementAfterSlotted => {
+                    ("invalid pseudo-element after ::slotted()", None)
+                }
+                InvalidState => ("invalid selector", None),
+                UnexpectedTokenInAttributeSelector(t) => (
+                    "unexpected token in attribute selector",
+                    Some(t.to_css_string()),
+                ),
+                PseudoElementExpectedColon(t) => (
+                    "expected a colon before the pseudo-element",
+                    Some(t.to_css_string()),
+                ),
+                PseudoElementExpectedIdent(t) => (
+                    "expected a pseudo-class or pseudo-element name",
+                    Some(t.to_css_string()),
+                ),
+                NoIdentForPseudo(t) => ("expected a pseudo-class name", Some(t.to_css_string())),
+                UnsupportedPseudoClassOrElement(name) => (
+                    "unsupported pseudo-class or pseudo-element",
+                    Some(name.to_string()),
+                ),
+                UnexpectedIdent(name) => ("unexpected identifier", Some(name.to_string())),
+                ExpectedNamespace(prefix) => {
+                    ("undeclared namespace prefix", Some(prefix.to_string()))
+                }
+                ExpectedBarInAttr(t) => (
+                    "expected `|` in attribute selector",
+                    Some(t.to_css_string()),
+                ),
+                BadValueInAttr(t) => (
+                    "invalid value in attribute selector",
+                    Some(t.to_css_string()),
+                ),
+                InvalidQualNameInAttr(t) => ("invalid attribute name", Some(t.to_css_string())),
+                ExplicitNamespaceUnexpectedToken(t) => {
+                    ("unexpected token after namespace", Some(t.to_css_string()))
+                }
+                ClassNeedsIdent(t) => ("expected a class name", Some(t.to_css_string())),
+                EmptyNegation => ("empty :not()", None),
+            },
+        };
+
+        SelectorError {
+            message: Cow::Borrowed(message),
+            token,
+            column: char_column(sel, e.location),
+        }
+    }
+}
+
+impl fmt::Display for SelectorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.token {
+            Some(ref token) => write!(f, "{} `{}` at column {}", self.message, token, self.column),
+            None => write!(f, "{} at column {}", self.message, self.column),
+        }
+    }
+}
+
+impl std::error::Error for SelectorError {}
+
+/// Converts a location reported by cssparser, whose columns are counted in
+/// UTF-16 code units within each line, into a column in characters counted
+/// from the start of the selector.
+fn char_column(sel: &str, location: SourceLocation) -> usize {
+    let mut line = 0;
+    let mut units = 1;
+    let mut chars = sel.chars().enumerate().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        if line == location.line && units >= location.column {
+            return i + 1;
+        }
+
+        match c {
+            '\r' if matches!(chars.peek(), Some((_, '\n'))) => (),
+            '\n' | '\r' | '\x0C' => {
+                line += 1;
+                units = 1;
+            }
+            _ => units += c.len_utf16() as u32,
+        }
+    }
+
+    sel.chars().count() + 1
+}
+
 #[derive(Debug, Clone)]
 pub struct Matches<T> {
+    /// The roots not walked yet, the next one last.
     roots: Vec<T>,
+    /// The nodes of the current walk, the next one last.
     nodes: Vec<T>,
     matcher: Matcher,
     set: HashSet<NodeId>,
     match_scope: MatchScope,
+    /// The candidates looked up in the tree's index instead of walking it.
+    candidates: Option<std::vec::IntoIter<T>>,
+    started: bool,
 }
 
 /// Telling a `matches` if we want to skip the roots.
@@ -56,13 +503,7 @@ pub enum MatchScope {
 
 impl<T> Matches<T> {
     pub fn from_one(node: T, matcher: Matcher, match_scope: MatchScope) -> Self {
-        Self {
-            roots: vec![node],
-            nodes: vec![],
-            matcher: matcher,
-            set: HashSet::new(),
-            match_scope,
-        }
+        Self::from_list(std::iter::once(node), matcher, match_scope)
     }
 
     pub fn from_list<I: Iterator<Item = T>>(
@@ -70,13 +511,70 @@ impl<T> Matches<T> {
         matcher: Matcher,
         match_scope: MatchScope,
     ) -> Self {
+        let mut roots: Vec<T> = nodes.collect();
+        roots.reverse();
+
         Self {
-            roots: nodes.collect(),
+            roots,
             nodes: vec![],
-            matcher: matcher,
+            matcher,
             set: HashSet::new(),
             match_scope,
+            candidates: None,
+            started: false,
+        }
+    }
+}
+
+impl<'a> Matches<NodeRef<'a, NodeData>> {
+    /// Looks up the candidates in the index of the tree, in document order.
+    /// Returns `None` if the matcher has no index keys or a root is not
+    /// attached to the tree, in which case the roots are walked.
+    fn seed(&self) -> Option<Vec<NodeRef<'a, NodeData>>> {
+        let tree = self.roots.first()?.tree;
+        let keys = self.matcher.index_keys(tree.quirks_mode())?;
+        let index = tree.index();
+        let order = tree.document_order();
+
+        let roots: HashSet<NodeId> = self.roots.iter().map(|root| root.id).collect();
+        if roots.iter().any(|id| order.position(id).is_none()) {
+            return None;
+        }
+
+        let mut ids: Vec<NodeId> = keys
+            .iter()
+            .flat_map(|key| index.get(key).iter().copied())
+            .collect();
+        ids.sort_by_key(|id| order.position(id));
+        ids.dedup();
+
+        // Everything attached to the tree is under its root.
+        let whole_tree = roots.contains(&tree.root_id());
+
+        Some(
+            ids.into_iter()
+                .map(|id| NodeRef::new(id, tree))
+                .filter(|node| whole_tree || self.in_scope(node, &roots))
+                .collect(),
+        )
+    }
+
+    fn in_scope(&self, node: &NodeRef<'a, NodeData>, roots: &HashSet<NodeId>) -> bool {
+        if let MatchScope::IncludeNode = self.match_scope {
+            if roots.contains(&node.id) {
+                return true;
+            }
         }
+
+        let mut parent = node.parent();
+        while let Some(node) = parent {
+            if roots.contains(&node.id) {
+                return true;
+            }
+            parent = node.parent();
+        }
+
+        false
     }
 }
 
@@ -84,52 +582,212 @@ impl<'a> Iterator for Matches<NodeRef<'a, NodeData>> {
     type Item = NodeRef<'a, NodeData>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        loop {
-            if self.nodes.is_empty() {
-                if self.roots.is_empty() {
-                    return None;
-                }
+        if !self.started {
+            self.started = true;
+            self.candidates = self.seed().map(Vec::into_iter);
+        }
 
-                let root = self.roots.remove(0);
+        if let Some(ref mut candidates) = self.candidates {
+            let matcher = &self.matcher;
+            return candidates.find(|node| matcher.match_element(node));
+        }
 
-                match self.match_scope {
-                    MatchScope::IncludeNode => self.nodes.insert(0, root),
-                    MatchScope::ChildrenOnly => {
-                        for child in root.children().into_iter().rev() {
-                            self.nodes.insert(0, child);
+        loop {
+            let node = match self.nodes.pop() {
+                Some(node) => node,
+                None => {
+                    let root = self.roots.pop()?;
+                    match self.match_scope {
+                        MatchScope::IncludeNode => self.nodes.push(root),
+                        MatchScope::ChildrenOnly => {
+                            self.nodes.extend(root.children().into_iter().rev())
                         }
                     }
+                    continue;
                 }
+            };
+
+            self.nodes.extend(node.children().into_iter().rev());
+
+            // Selectors like `*` or `:not(li)` would match text and comments.
+            if node.is_element() && self.matcher.match_element(&node) && self.set.insert(node.id) {
+                return Some(node);
             }
+        }
+    }
+}
 
-            while !self.nodes.is_empty() {
-                let node = self.nodes.remove(0);
+#[derive(Clone, Copy)]
+pub(crate) struct InnerSelectorParser<'a> {
+    namespaces: &'a HashMap<Prefix, Namespace>,
+    /// The namespace of type selectors without a prefix.
+    default_namespace: Option<&'a Namespace>,
+    /// Whether to accept positional pseudo-classes like `:eq(2)`.
+    positional: bool,
+}
 
-                for node in node.children().into_iter().rev() {
-                    self.nodes.insert(0, node);
-                }
+impl<'i, 'a> parser::Parser<'i> for InnerSelectorParser<'a> {
+    type Impl = InnerSelector;
+    type Error = InnerParseErrorKind<'i>;
 
-                if self.matcher.match_element(&node) {
-                    if self.set.contains(&node.id) {
-                        continue;
-                    }
+    fn namespace_for_prefix(&self, prefix: &LocalName) -> Option<Namespace> {
+        self.namespaces.get(&Prefix::from(&**prefix)).cloned()
+    }
+
+    fn default_namespace(&self) -> Option<Namespace> {
+        self.default_namespace.cloned()
+    }
+
+    fn parse_non_ts_pseudo_class(
+        &self,
+        location: SourceLocation,
+        name: CowRcStr<'i>,
+    ) -> Result<NonTSPseudoClass, ParseError<'i, Self::Error>> {
+        use NonTSPseudoClass::*;
+
+        let pseudo_class = match &*name.to_ascii_lowercase() {
+            "any-link" => AnyLink,
+            "link" => Link,
+            "checked" => Checked,
+            "disabled" => Disabled,
+            "enabled" => Enabled,
+            "required" => Required,
+            "optional" => Optional,
+            "read-only" => ReadOnly,
+            "read-write" => ReadWrite,
+            "placeholder-shown" => PlaceholderShown,
+            "first" if self.positional => Position(positional::Position::First),
+            "last" if self.positional => Position(positional::Position::Last),
+            "even" if self.positional => Position(positional::Position::Even),
+            "odd" if self.positional => Position(positional::Position::Odd),
+            _ => {
+                return Err(location.new_custom_error(
+                    SelectorParseErrorKind::UnsupportedPseudoClassOrElement(name),
+                ))
+            }
+        };
+
+        Ok(pseudo_class)
+    }
 
-                    self.set.insert(node.id);
-                    return Some(node);
+    fn parse_non_ts_functional_pseudo_class<'t>(
+        &self,
+        name: CowRcStr<'i>,
+        arguments: &mut cssparser::Parser<'i, 't>,
+    ) -> Result<NonTSPseudoClass, ParseError<'i, Self::Error>> {
+        match &*name.to_ascii_lowercase() {
+            "contains" => Ok(NonTSPseudoClass::Contains(
+                arguments.expect_ident_or_string()?.to_string(),
+            )),
+            "containsown" => Ok(NonTSPseudoClass::ContainsOwn(
+                arguments.expect_ident_or_string()?.to_string(),
+            )),
+            // The positional pseudo-classes have no meaning inside `:has()`.
+            "has" => Ok(NonTSPseudoClass::Has(
+                InnerSelectorParser {
+                    positional: false,
+                    ..*self
                 }
+                .parse_relative_selectors(arguments)?,
+            )),
+            "eq" if self.positional => Ok(NonTSPseudoClass::Position(positional::Position::Eq(
+                arguments.expect_integer()?,
+            ))),
+            "gt" if self.positional => Ok(NonTSPseudoClass::Position(positional::Position::Gt(
+                arguments.expect_integer()?,
+            ))),
+            "lt" if self.positional => Ok(NonTSPseudoClass::Position(positional::Position::Lt(
+                arguments.expect_integer()?,
+            ))),
+            #[cfg(feature = "regex")]
+            "matches" => Ok(NonTSPseudoClass::Matches(parse_pattern(arguments)?)),
+            #[cfg(feature = "regex")]
+            "matchesown" => Ok(NonTSPseudoClass::MatchesOwn(parse_pattern(arguments)?)),
+            _ => Err(arguments.new_custom_error(
+                SelectorParseErrorKind::UnsupportedPseudoClassOrElement(name),
+            )),
+        }
+    }
+}
+
+impl<'a> InnerSelectorParser<'a> {
+    fn new(namespaces: &'a HashMap<Prefix, Namespace>) -> Self {
+        InnerSelectorParser {
+            namespaces,
+            default_namespace: None,
+            positional: false,
+        }
+    }
+
+    /// Parses the comma separated relative selectors of `:has()`.
+    fn parse_relative_selectors<'i, 't>(
+        &self,
+        arguments: &mut cssparser::Parser<'i, 't>,
+    ) -> Result<Vec<RelativeSelector>, ParseError<'i, InnerParseErrorKind<'i>>> {
+        let mut selectors = vec![];
+
+        loop {
+            arguments.skip_whitespace();
+            let location = arguments.current_source_location();
+            let start = arguments.position();
+            let source = arguments.parse_until_before(Delimiter::Comma, |input| {
+                while input.next_including_whitespace().is_ok() {}
+                Ok::<_, ParseError<'i, InnerParseErrorKind<'i>>>(input.slice_from(start).trim())
+            })?;
+
+            if source.is_empty() {
+                return Err(location.new_custom_error(SelectorParseErrorKind::EmptySelector));
+            }
+
+            let selector = RelativeSelector::parse_with(source, self).map_err(|e| {
+                location.new_custom_error(InnerParseErrorKind::Relative(Box::new(e)))
+            })?;
+            selectors.push(selector);
+
+            if arguments.next().is_err() {
+                return Ok(selectors);
             }
         }
     }
 }
 
-pub(crate) struct InnerSelectorParser;
+/// The errors `InnerSelectorParser` reports besides the ones of `selectors`.
+#[derive(Debug)]
+pub(crate) enum InnerParseErrorKind<'i> {
+    Selector(SelectorParseErrorKind<'i>),
+    /// An error in a relative selector of `:has()`.
+    Relative(Box<SelectorError>),
+    #[cfg(feature = "regex")]
+    InvalidRegex(String),
+}
 
-impl<'i> parser::Parser<'i> for InnerSelectorParser {
-    type Impl = InnerSelector;
-    type Error = parser::SelectorParseErrorKind<'i>;
+impl<'i> From<SelectorParseErrorKind<'i>> for InnerParseErrorKind<'i> {
+    fn from(kind: SelectorParseErrorKind<'i>) -> Self {
+        InnerParseErrorKind::Selector(kind)
+    }
 }
 
-#[derive(Debug, Clone)]
+/// Parses the regular expression of `:matches()`, either quoted or as is.
+#[cfg(feature = "regex")]
+fn parse_pattern<'i, 't>(
+    arguments: &mut cssparser::Parser<'i, 't>,
+) -> Result<TextPattern, ParseError<'i, InnerParseErrorKind<'i>>> {
+    let start = arguments.position();
+    let pattern = match arguments.try_parse(|input| input.expect_string_cloned()) {
+        Ok(pattern) => pattern.to_string(),
+        Err(_) => {
+            while arguments.next_including_whitespace().is_ok() {}
+            arguments.slice_from(start).trim().to_string()
+        }
+    };
+
+    match Regex::new(&pattern) {
+        Ok(regex) => Ok(TextPattern(regex)),
+        Err(_) => Err(arguments.new_custom_error(InnerParseErrorKind::InvalidRegex(pattern))),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct InnerSelector;
 
 impl parser::SelectorImpl for InnerSelector {
@@ -148,8 +806,278 @@ impl parser::SelectorImpl for InnerSelector {
     type PseudoElement = PseudoElement;
 }
 
-#[derive(Clone, Eq, PartialEq)]
-pub struct NonTSPseudoClass;
+/// The pseudo-classes which are not about the tree structure. Form and link
+/// states are evaluated from the attributes of elements, as they would be
+/// right after the document loaded.
+///
+/// Like in jQuery, `:contains()` matches elements whose text contains the
+/// given string, case-sensitively, while `:containsOwn()` only looks at the
+/// text nodes directly under the element. With the `regex` feature,
+/// `:matches()` and `:matchesOwn()` do the same with a regular expression.
+///
+/// `:has()` matches elements relative to which any of its selectors match,
+/// so `tr:has(> td.price)` matches the rows with a price cell.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum NonTSPseudoClass {
+    Contains(String),
+    ContainsOwn(String),
+    Has(Vec<RelativeSelector>),
+    /// Filters the matched set rather than matching single elements, see
+    /// the `positional` module.
+    Position(positional::Position),
+    #[cfg(feature = "regex")]
+    Matches(TextPattern),
+    #[cfg(feature = "regex")]
+    MatchesOwn(TextPattern),
+    AnyLink,
+    Link,
+    Checked,
+    Disabled,
+    Enabled,
+    Required,
+    Optional,
+    ReadOnly,
+    ReadWrite,
+    PlaceholderShown,
+}
+
+/// What relative selectors are prefixed with to be parsed as complete selectors.
+/// The scope is in any namespace, whatever the default namespace is.
+const SCOPE_PREFIX: &str = "*|*:scope ";
+
+/// A selector of `:has()`, relative to the element being matched. Like in
+/// `:has(> img)`, it may start with a combinator, the descendant one otherwise.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RelativeSelector {
+    source: String,
+    selector_list: SelectorList<InnerSelector>,
+    /// Whether it starts with a sibling combinator, `+` or `~`.
+    siblings: bool,
+    /// Whether it was prefixed with `SCOPE_PREFIX` to be parsed.
+    prefixed: bool,
+}
+
+impl RelativeSelector {
+    pub(crate) fn parse(
+        source: &str,
+        namespaces: &HashMap<Prefix, Namespace>,
+    ) -> Result<Self, SelectorError> {
+        Self::parse_with(source, &InnerSelectorParser::new(namespaces))
+    }
+
+    fn parse_with(source: &str, parser: &InnerSelectorParser) -> Result<Self, SelectorError> {
+        // Like `:scope > li`, which is relative to the scope already.
+        if !source.starts_with(['>', '+', '~']) && source.to_ascii_lowercase().contains(":scope") {
+            if let Ok(matcher) = Matcher::parse(source, parser) {
+                if matcher.uses_scope() {
+                    return Ok(RelativeSelector {
+                        source: source.to_string(),
+                        selector_list: matcher.selector_list,
+                        siblings: false,
+                        prefixed: false,
+                    });
+                }
+            }
+        }
+
+        let matcher = Matcher::parse(&format!("{}{}", SCOPE_PREFIX, source), parser)?;
+
+        Ok(RelativeSelector {
+            source: source.to_string(),
+            selector_list: matcher.selector_list,
+            siblings: source.starts_with(['+', '~']),
+            prefixed: true,
+        })
+    }
+
+    /// The specificity of the most specific of the comma separated selectors,
+    /// leaving out the `:scope` of the prefix.
+    fn specificity(&self) -> Specificity {
+        let mut specificity = self
+            .selector_list
+            .0
+            .iter()
+            .map(specificity_of)
+            .max()
+            .unwrap_or_default();
+        if self.prefixed {
+            specificity.classes -= 1;
+        }
+        specificity
+    }
+
+    /// Whether any element relative to `scope` matches.
+    pub(crate) fn matches(&self, scope: &NodeRef<NodeData>, quirks_mode: QuirksMode) -> bool {
+        !self.find(scope, quirks_mode, true).is_empty()
+    }
+
+    /// Finds the elements relative to `scope` which match, in document order,
+    /// stopping at the first one if `first_only`.
+    pub(crate) fn find<'a>(
+        &self,
+        scope: &NodeRef<'a, NodeData>,
+        quirks_mode: QuirksMode,
+        first_only: bool,
+    ) -> Vec<NodeRef<'a, NodeData>> {
+        let mut ctx =
+            matching::MatchingContext::new(matching::MatchingMode::Normal, None, None, quirks_mode);
+        ctx.scope_element = Some(scope.opaque());
+
+        let mut roots = vec![];
+        if self.siblings {
+            let mut sibling = scope.next_element_sibling();
+            while let Some(node) = sibling {
+                sibling = node.next_element_sibling();
+                roots.push(node);
+            }
+        } else {
+            roots.extend(scope.children());
+        }
+
+        let mut found = vec![];
+        let mut stack: Vec<_> = roots.into_iter().rev().collect();
+        while let Some(node) = stack.pop() {
+            if !node.is_element() {
+                continue;
+            }
+
+            stack.extend(node.children().into_iter().rev());
+
+            if matching::matches_selector_list(&self.selector_list, &node, &mut ctx) {
+                found.push(node);
+                if first_only {
+                    break;
+                }
+            }
+        }
+
+        found
+    }
+}
+
+/// The selectors `Selection::select` matches relative to each element of the
+/// selection, when one of them starts with a combinator, like `> li`, or uses
+/// `:scope`. The others are then relative to the descendants of each element,
+/// so `:scope` never matches above it.
+pub(crate) struct ScopedSelector {
+    selectors: Vec<RelativeSelector>,
+}
+
+impl ScopedSelector {
+    /// Parses `sel`, or returns `None` if none of its selectors starts with a
+    /// combinator or uses `:scope`.
+    pub(crate) fn parse(
+        sel: &str,
+        namespaces: &HashMap<Prefix, Namespace>,
+    ) -> Result<Option<Self>, SelectorError> {
+        Self::parse_with(sel, &InnerSelectorParser::new(namespaces))
+    }
+
+    /// Works like `parse` but also accepts the positional pseudo-classes,
+    /// which only the `positional` module can evaluate.
+    pub(crate) fn with_positional(
+        sel: &str,
+        namespaces: &HashMap<Prefix, Namespace>,
+    ) -> Result<Option<Self>, SelectorError> {
+        Self::parse_with(
+            sel,
+            &InnerSelectorParser {
+                positional: true,
+                ..InnerSelectorParser::new(namespaces)
+            },
+        )
+    }
+
+    fn parse_with(sel: &str, parser: &InnerSelectorParser) -> Result<Option<Self>, SelectorError> {
+        if !is_scoped(sel) {
+            return Ok(None);
+        }
+
+        let mut input = cssparser::ParserInput::new(sel);
+        let mut input = cssparser::Parser::new(&mut input);
+        input
+            .parse_entirely(|input| parser.parse_relative_selectors(input))
+            .map(|selectors| Some(ScopedSelector { selectors }))
+            .map_err(|e| SelectorError::new(sel, e))
+    }
+
+    /// Finds the elements relative to any of `scopes`, in document order.
+    pub(crate) fn select<'a>(
+        &self,
+        scopes: &[NodeRef<'a, NodeData>],
+    ) -> Vec<NodeRef<'a, NodeData>> {
+        let mut selected = vec![];
+        let mut set = HashSet::new();
+
+        for scope in scopes {
+            for selector in &self.selectors {
+                selected.extend(
+                    selector
+                        .find(scope, scope.tree.quirks_mode(), false)
+                        .into_iter()
+                        .filter(|node| set.insert(node.id)),
+                );
+            }
+        }
+
+        sort_in_document_order(&mut selected);
+        selected
+    }
+}
+
+/// Whether a selector of `sel` starts with a combinator or `sel` uses
+/// `:scope`, outside of functional pseudo-classes.
+pub(crate) fn is_scoped(sel: &str) -> bool {
+    let mut input = cssparser::ParserInput::new(sel);
+    let mut input = cssparser::Parser::new(&mut input);
+    let mut group_start = true;
+    let mut after_colon = false;
+
+    while let Ok(token) = input.next() {
+        match *token {
+            cssparser::Token::Delim('>')
+            | cssparser::Token::Delim('+')
+            | cssparser::Token::Delim('~')
+                if group_start =>
+            {
+                return true
+            }
+            cssparser::Token::Ident(ref name)
+                if after_colon && name.eq_ignore_ascii_case("scope") =>
+            {
+                return true
+            }
+            _ => (),
+        }
+
+        group_start = *token == cssparser::Token::Comma;
+        after_colon = *token == cssparser::Token::Colon;
+    }
+
+    false
+}
+
+/// A regular expression matched against the text of elements.
+#[cfg(feature = "regex")]
+#[derive(Clone, Debug)]
+pub struct TextPattern(Regex);
+
+#[cfg(feature = "regex")]
+impl TextPattern {
+    pub(crate) fn is_match(&self, text: &str) -> bool {
+        self.0.is_match(text)
+    }
+}
+
+#[cfg(feature = "regex")]
+impl PartialEq for TextPattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.as_str() == other.0.as_str()
+    }
+}
+
+#[cfg(feature = "regex")]
+impl Eq for TextPattern {}
 
 impl parser::NonTSPseudoClass for NonTSPseudoClass {
     type Impl = InnerSelector;
@@ -183,10 +1111,55 @@ impl cssparser::ToCss for NonTSPseudoClass {
     where
         W: fmt::Write,
     {
-        dest.write_str("")
+        let name = match self {
+            NonTSPseudoClass::Contains(text) => return write_function(dest, "contains", text),
+            NonTSPseudoClass::ContainsOwn(text) => {
+                return write_function(dest, "containsOwn", text)
+            }
+            NonTSPseudoClass::Position(position) => return position.to_css(dest),
+            NonTSPseudoClass::Has(selectors) => {
+                dest.write_str(":has(")?;
+                for (i, selector) in selectors.iter().enumerate() {
+                    if i > 0 {
+                        dest.write_str(", ")?;
+                    }
+                    dest.write_str(&selector.source)?;
+                }
+                return dest.write_char(')');
+            }
+            #[cfg(feature = "regex")]
+            NonTSPseudoClass::Matches(pattern) => {
+                return write_function(dest, "matches", pattern.0.as_str())
+            }
+            #[cfg(feature = "regex")]
+            NonTSPseudoClass::MatchesOwn(pattern) => {
+                return write_function(dest, "matchesOwn", pattern.0.as_str())
+            }
+            NonTSPseudoClass::AnyLink => ":any-link",
+            NonTSPseudoClass::Link => ":link",
+            NonTSPseudoClass::Checked => ":checked",
+            NonTSPseudoClass::Disabled => ":disabled",
+            NonTSPseudoClass::Enabled => ":enabled",
+            NonTSPseudoClass::Required => ":required",
+            NonTSPseudoClass::Optional => ":optional",
+            NonTSPseudoClass::ReadOnly => ":read-only",
+            NonTSPseudoClass::ReadWrite => ":read-write",
+            NonTSPseudoClass::PlaceholderShown => ":placeholder-shown",
+        };
+
+        dest.write_str(name)
     }
 }
 
+/// Writes a functional pseudo-class taking a string.
+fn write_function<W: fmt::Write>(dest: &mut W, name: &str, argument: &str) -> fmt::Result {
+    dest.write_char(':')?;
+    dest.write_str(name)?;
+    dest.write_char('(')?;
+    cssparser::serialize_string(argument, dest)?;
+    dest.write_char(')')
+}
+
 #[derive(Clone, Eq, PartialEq)]
 pub struct PseudoElement;
 
@@ -0,0 +1,255 @@
+use crate::dom_tree::{Node, NodeData, NodeId};
+use crate::matcher::Matcher;
+use crate::Document;
+use crate::Selection;
+use html5ever::LocalName;
+use selectors::bloom::BloomFilter;
+use selectors::matching::{MatchingContext, MatchingMode, QuirksMode};
+use selectors::parser::AncestorHashes;
+use selectors::NthIndexCache;
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use std::iter::FromIterator;
+
+/// A set of matchers, each with a key, which finds the elements matching any
+/// of them in a single walk of the tree.
+///
+/// While walking, a bloom filter of the ids, classes and names of the
+/// ancestors of the current element rejects most selectors whose ancestors
+/// cannot match without evaluating them.
+///
+/// # Examples
+///
+/// ```
+/// use nipper::{Document, Matcher, MatcherSet};
+///
+/// let document = Document::from(r#"<div class="post"><h2>Title</h2><a href="/more">More</a></div>"#);
+///
+/// let mut set = MatcherSet::new();
+/// set.add("title", Matcher::new(".post h2").unwrap());
+/// set.add("links", Matcher::new(".post a[href]").unwrap());
+///
+/// let found = set.select(&document);
+/// assert_eq!(found["title"].text().to_string(), "Title");
+/// assert_eq!(found["links"].attr("href").unwrap().to_string(), "/more");
+/// ```
+#[derive(Debug, Clone)]
+pub struct MatcherSet<K> {
+    /// The distinct keys.
+    keys: Vec<K>,
+    matchers: Vec<Matcher>,
+    /// The `ancestor_hashes` of each matcher, outside of quirks mode.
+    hashes: Vec<Vec<AncestorHashes>>,
+    /// The `ancestor_hashes` of each matcher, in quirks mode.
+    quirks_hashes: Vec<Vec<AncestorHashes>>,
+    /// The index of the key of each matcher.
+    groups: Vec<usize>,
+}
+
+impl<K> Default for MatcherSet<K> {
+    fn default() -> Self {
+        Self {
+            keys: vec![],
+            matchers: vec![],
+            hashes: vec![],
+            quirks_hashes: vec![],
+            groups: vec![],
+        }
+    }
+}
+
+impl<K: Clone + Eq + Hash> MatcherSet<K> {
+    /// Creates an empty set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a matcher. The elements matching matchers added with the same key
+    /// are grouped together.
+    pub fn add(&mut self, key: K, matcher: Matcher) {
+        let group = match self.keys.iter().position(|k| *k == key) {
+            Some(group) => group,
+            None => {
+                self.keys.push(key);
+                self.keys.len() - 1
+            }
+        };
+
+        self.hashes
+            .push(matcher.ancestor_hashes(QuirksMode::NoQuirks));
+        self.quirks_hashes
+            .push(matcher.ancestor_hashes(QuirksMode::Quirks));
+        self.matchers.push(matcher);
+        self.groups.push(group);
+    }
+
+    /// The number of matchers in the set.
+    pub fn len(&self) -> usize {
+        self.matchers.len()
+    }
+
+    /// Whether the set has no matchers.
+    pub fn is_empty(&self) -> bool {
+        self.matchers.is_empty()
+    }
+
+    /// Finds the elements of the document matching each matcher, grouped by
+    /// key, in document order. Every key is in the result, with an empty
+    /// selection if nothing matched.
+    pub fn select<'a>(&self, document: &'a Document) -> HashMap<K, Selection<'a>> {
+        self.select_from(&[document.tree.root()])
+    }
+
+    /// Works like `select` but only looks at the descendants of each element
+    /// of `selection`, like `Selection::select` does.
+    pub fn select_in<'a>(&self, selection: &Selection<'a>) -> HashMap<K, Selection<'a>> {
+        self.select_from(&selection.nodes)
+    }
+
+    fn select_from<'a>(&self, roots: &[Node<'a>]) -> HashMap<K, Selection<'a>> {
+        let mut found = vec![vec![]; self.keys.len()];
+        let mut bloom = BloomFilter::new();
+        let mut nth_index_cache = NthIndexCache::default();
+        let mut seen = HashSet::new();
+
+        for root in roots {
+            // The descendants of a root inside an earlier one are already walked.
+            if seen.contains(&root.id) {
+                continue;
+            }
+
+            bloom.clear();
+            let mut ancestor = Some(root.clone());
+            while let Some(node) = ancestor {
+                for hash in element_hashes(&node) {
+                    bloom.insert_hash(hash);
+                }
+                ancestor = node.parent();
+            }
+
+            self.walk(
+                root,
+                &mut bloom,
+                &mut nth_index_cache,
+                &mut seen,
+                &mut found,
+            );
+        }
+
+        self.keys
+            .iter()
+            .cloned()
+            .zip(found.into_iter().map(Selection::in_document_order))
+            .collect()
+    }
+
+    /// Walks the descendants of `root` in document order, with `bloom` holding
+    /// the ancestors of the current element.
+    fn walk<'a>(
+        &self,
+        root: &Node<'a>,
+        bloom: &mut BloomFilter,
+        nth_index_cache: &mut NthIndexCache,
+        seen: &mut HashSet<NodeId>,
+        found: &mut [Vec<Node<'a>>],
+    ) {
+        let mut stack: Vec<Visit> = root
+            .children()
+            .into_iter()
+            .rev()
+            .map(Visit::Enter)
+            .collect();
+
+        while let Some(visit) = stack.pop() {
+            let node = match visit {
+                Visit::Enter(node) => node,
+                Visit::Leave(hashes) => {
+                    for hash in hashes {
+                        bloom.remove_hash(hash);
+                    }
+                    continue;
+                }
+            };
+
+            if !node.is_element() || !seen.insert(node.id) {
+                continue;
+            }
+
+            {
+                let quirks_mode = node.tree.quirks_mode();
+                let hashes = match quirks_mode {
+                    QuirksMode::Quirks => &self.quirks_hashes,
+                    _ => &self.hashes,
+                };
+                let mut ctx = MatchingContext::new(
+                    MatchingMode::Normal,
+                    Some(bloom),
+                    Some(nth_index_cache),
+                    quirks_mode,
+                );
+
+                for (i, matcher) in self.matchers.iter().enumerate() {
+                    let nodes = &mut found[self.groups[i]];
+                    // Another matcher with the same key matched already.
+                    if matches!(nodes.last(), Some(last) if last.id == node.id) {
+                        continue;
+                    }
+
+                    if matcher.match_element_with(&node, &hashes[i], &mut ctx) {
+                        nodes.push(node.clone());
+                    }
+                }
+            }
+
+            let hashes = element_hashes(&node);
+            for &hash in &hashes {
+                bloom.insert_hash(hash);
+            }
+
+            stack.push(Visit::Leave(hashes));
+            stack.extend(node.children().into_iter().rev().map(Visit::Enter));
+        }
+    }
+}
+
+impl<K: Clone + Eq + Hash> FromIterator<(K, Matcher)> for MatcherSet<K> {
+    fn from_iter<I: IntoIterator<Item = (K, Matcher)>>(iter: I) -> Self {
+        let mut set = Self::new();
+        for (key, matcher) in iter {
+            set.add(key, matcher);
+        }
+        set
+    }
+}
+
+enum Visit<'a> {
+    Enter(Node<'a>),
+    /// The hashes the element added to the bloom filter, once its
+    /// descendants are walked.
+    Leave(Vec<u32>),
+}
+
+/// The hashes of the name, namespace, id and classes of an element, the
+/// things `AncestorHashes` are computed from.
+fn element_hashes(node: &Node) -> Vec<u32> {
+    node.query(|node| match node.data {
+        NodeData::Element(ref e) => {
+            let mut hashes = vec![e.name.local.get_hash(), e.name.ns.get_hash()];
+
+            for attr in &e.attrs {
+                match &*attr.name.local {
+                    "id" => hashes.push(LocalName::from(&*attr.value).get_hash()),
+                    "class" => hashes.extend(
+                        attr.value
+                            .split_whitespace()
+                            .map(|class| LocalName::from(class).get_hash()),
+                    ),
+                    _ => (),
+                }
+            }
+
+            hashes
+        }
+        _ => vec![],
+    })
+}
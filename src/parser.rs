@@ -0,0 +1,460 @@
+use crate::dom_tree::NodeId;
+use crate::encoding;
+use crate::position::PositionSink;
+use crate::Document;
+use encoding_rs::{Decoder, Encoding};
+#[cfg(feature = "async")]
+use futures_io::AsyncRead;
+use html5ever::tokenizer::{BufferQueue, Tokenizer, TokenizerOpts, TokenizerResult};
+use html5ever::tree_builder::{create_element, TreeBuilder, TreeBuilderOpts, TreeSink};
+use html5ever::ParseOpts;
+use markup5ever::interface::tree_builder::NoQuirks;
+use markup5ever::QualName;
+use markup5ever::{namespace_url, ns, LocalName};
+use std::borrow::Cow;
+use std::io::{self, ErrorKind, Read};
+use std::mem;
+#[cfg(feature = "async")]
+use std::{future::poll_fn, pin::Pin};
+use tendril::StrTendril;
+use tendril::TendrilSink;
+
+/// Options for parsing HTML into a `Document`.
+///
+/// # Examples
+///
+/// ```
+/// use nipper::ParseOptions;
+///
+/// let html = "<body><noscript><img src=\"fallback.png\"></noscript></body>";
+///
+/// let document = ParseOptions::new().parse(html);
+/// assert!(!document.select("noscript img").exists());
+///
+/// let document = ParseOptions::new().scripting_enabled(false).parse(html);
+/// assert!(document.select("noscript img").exists());
+/// ```
+#[derive(Debug, Clone)]
+pub struct ParseOptions {
+    scripting_enabled: bool,
+    exact_errors: bool,
+    iframe_srcdoc: bool,
+    drop_doctype: bool,
+    discard_bom: bool,
+    track_positions: bool,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            scripting_enabled: true,
+            exact_errors: false,
+            iframe_srcdoc: false,
+            drop_doctype: false,
+            discard_bom: true,
+            track_positions: false,
+        }
+    }
+}
+
+impl ParseOptions {
+    /// Creates the default options, the ones used by `Document::from`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether scripting is enabled, default true. When scripting is
+    /// disabled, the contents of `<noscript>` are parsed as markup instead of
+    /// raw text.
+    pub fn scripting_enabled(mut self, enabled: bool) -> Self {
+        self.scripting_enabled = enabled;
+        self
+    }
+
+    /// Whether to report all parse errors described in the spec, at some
+    /// performance penalty. Default false.
+    pub fn exact_errors(mut self, exact: bool) -> Self {
+        self.exact_errors = exact;
+        self
+    }
+
+    /// Whether the input is the `srcdoc` of an `<iframe>`, default false.
+    /// Such documents never trigger quirks mode.
+    pub fn iframe_srcdoc(mut self, srcdoc: bool) -> Self {
+        self.iframe_srcdoc = srcdoc;
+        self
+    }
+
+    /// Whether to drop the `DOCTYPE` from the tree, default false.
+    pub fn drop_doctype(mut self, drop: bool) -> Self {
+        self.drop_doctype = drop;
+        self
+    }
+
+    /// Whether to discard a leading `U+FEFF BYTE ORDER MARK`, default true.
+    pub fn discard_bom(mut self, discard: bool) -> Self {
+        self.discard_bom = discard;
+        self
+    }
+
+    /// Whether to record where each element starts in the source, default
+    /// false. See `Node::source_position`. Parse errors then have a byte
+    /// offset and column too. Parsing is slower, as the tokenizer is fed a
+    /// character at a time.
+    pub fn track_positions(mut self, track: bool) -> Self {
+        self.track_positions = track;
+        self
+    }
+
+    /// Parses an HTML document.
+    pub fn parse<T>(&self, html: T) -> Document
+    where
+        T: Into<StrTendril>,
+    {
+        self.document_parser().one(html)
+    }
+
+    /// Parses an HTML document from bytes. See `Document::from_bytes_with_charset`
+    /// for how the character encoding is determined.
+    pub fn parse_bytes(&self, bytes: &[u8], transport_charset: Option<&str>) -> Document {
+        let encoding = encoding::sniff(bytes, transport_charset);
+        let (html, _) = encoding.decode_with_bom_removal(bytes);
+
+        let mut document = self.parse(&*html);
+        document.encoding = encoding;
+        document
+    }
+
+    /// Parses an HTML document from a reader, decoding it like `parse_bytes`
+    /// while reading, so the whole input is never held in memory at once.
+    pub fn parse_reader<R: Read>(&self, mut reader: R) -> io::Result<Document> {
+        let mut parser = self.parser();
+        let mut buf = [0; 8192];
+
+        loop {
+            match reader.read(&mut buf) {
+                Ok(0) => return Ok(parser.finish()),
+                Ok(n) => parser.feed(&buf[..n]),
+                Err(ref e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Works like `parse_reader` but reads from an asynchronous reader, parsing
+    /// each chunk as soon as it arrives.
+    ///
+    /// Like `Document` itself, the returned future is not `Send`.
+    #[cfg(feature = "async")]
+    pub async fn parse_async_reader<R>(&self, mut reader: R) -> io::Result<Document>
+    where
+        R: AsyncRead + Unpin,
+    {
+        let mut parser = self.parser();
+        let mut buf = [0; 8192];
+
+        loop {
+            match poll_fn(|cx| Pin::new(&mut reader).poll_read(cx, &mut buf)).await {
+                Ok(0) => return Ok(parser.finish()),
+                Ok(n) => parser.feed(&buf[..n]),
+                Err(ref e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Creates an incremental parser which is fed the document chunk by chunk.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use nipper::ParseOptions;
+    ///
+    /// let mut parser = ParseOptions::new().parser();
+    /// parser.feed(b"<ul><li>One</li><l");
+    /// parser.feed(b"i>Two</li></ul>");
+    /// let document = parser.finish();
+    /// assert_eq!(document.select("li").length(), 2);
+    /// ```
+    pub fn parser(&self) -> DocumentParser {
+        self.parser_with_charset(None)
+    }
+
+    /// Works like `parser` but takes the charset given by the transport layer
+    /// into account. See `Document::from_bytes_with_charset`.
+    pub fn parser_with_charset(&self, transport_charset: Option<&str>) -> DocumentParser {
+        DocumentParser {
+            parser: self.document_parser(),
+            transport_charset: transport_charset.map(String::from),
+            sniff_buffer: vec![],
+            prescanned: false,
+            decoder: None,
+        }
+    }
+
+    /// Parses an HTML fragment. See `Document::fragment`.
+    pub fn parse_fragment<T>(&self, html: T, context: &str) -> Document
+    where
+        T: Into<StrTendril>,
+    {
+        let document = self.fragment_parser(context).one(html);
+
+        // `parse_fragment` puts everything under a synthetic <html> root.
+        if let Some(html) = document.tree.first_child_of(&document.tree.root_id()) {
+            let html_id = html.id;
+            document.tree.remove_from_parent(&html_id);
+            document
+                .tree
+                .reparent_children_of(&html_id, Some(document.tree.root_id()));
+        }
+
+        document
+    }
+
+    fn document_parser(&self) -> HtmlParser {
+        let opts = ParseOpts::from(self);
+        let builder = TreeBuilder::new(Document::default(), opts.tree_builder);
+        HtmlParser::new(builder, opts.tokenizer, self.track_positions)
+    }
+
+    /// Creates a fragment parser whose context element is named `context`.
+    ///
+    /// The parsed document looks like:
+    /// <:root>             id -> 0
+    ///   <context>         id -> 1 (detached)
+    ///   <html>            id -> 2
+    ///     things we need.
+    ///   </html>
+    /// <:root>
+    pub(crate) fn fragment_parser(&self, context: &str) -> HtmlParser {
+        let opts = ParseOpts::from(self);
+        let mut document = Document::default();
+        let name = QualName::new(None, ns!(html), LocalName::from(context));
+        let context = create_element(&mut document, name, Vec::new());
+
+        let builder = TreeBuilder::new_for_fragment(document, context, None, opts.tree_builder);
+        let tokenizer_opts = TokenizerOpts {
+            initial_state: Some(builder.tokenizer_state_for_context_elem()),
+            ..opts.tokenizer
+        };
+        HtmlParser::new(builder, tokenizer_opts, self.track_positions)
+    }
+}
+
+impl From<&ParseOptions> for ParseOpts {
+    fn from(opts: &ParseOptions) -> ParseOpts {
+        ParseOpts {
+            tokenizer: TokenizerOpts {
+                exact_errors: opts.exact_errors,
+                discard_bom: opts.discard_bom,
+                ..Default::default()
+            },
+            tree_builder: TreeBuilderOpts {
+                exact_errors: opts.exact_errors,
+                scripting_enabled: opts.scripting_enabled,
+                iframe_srcdoc: opts.iframe_srcdoc,
+                drop_doctype: opts.drop_doctype,
+                ignore_missing_rules: false,
+                quirks_mode: NoQuirks,
+            },
+        }
+    }
+}
+
+/// An incremental HTML parser, created by `ParseOptions::parser`.
+///
+/// The character encoding is determined the same way `Document::from_bytes`
+/// does, after which every chunk is decoded and parsed as soon as it is fed.
+/// Unless a byte order mark or the transport layer charset settle it, the
+/// first 1024 bytes are held back until then, and then anything from the
+/// first non-ASCII byte on until there are 1024 bytes to guess the encoding
+/// from.
+pub struct DocumentParser {
+    parser: HtmlParser,
+    transport_charset: Option<String>,
+
+    /// Bytes held back until the encoding is known.
+    sniff_buffer: Vec<u8>,
+    prescanned: bool,
+    decoder: Option<Decoder>,
+}
+
+impl DocumentParser {
+    /// Feeds the next chunk of bytes. Chunks may split characters and tags anywhere.
+    pub fn feed(&mut self, chunk: &[u8]) {
+        if self.decoder.is_some() {
+            self.decode(chunk, false);
+            return;
+        }
+
+        self.sniff_buffer.extend_from_slice(chunk);
+        self.sniff(false);
+    }
+
+    /// Signals the end of the input and returns the parsed document.
+    pub fn finish(mut self) -> Document {
+        if self.decoder.is_none() {
+            self.sniff(true);
+        }
+        self.decode(&[], true);
+
+        let mut document = self.parser.finish();
+        if let Some(decoder) = self.decoder {
+            document.encoding = decoder.encoding();
+        }
+        document
+    }
+
+    /// Starts decoding once the encoding is known, `last` telling whether
+    /// the input ended.
+    fn sniff(&mut self, last: bool) {
+        if !self.prescanned {
+            let transport_charset = self.transport_charset.as_deref();
+
+            // Nothing is held back when a byte order mark or the transport
+            // layer settle the encoding.
+            if let Some(encoding) =
+                encoding::sniff_bom_or_transport(&self.sniff_buffer, transport_charset, last)
+            {
+                self.prescanned = true;
+                return self.start_decoding(encoding);
+            }
+
+            if self.sniff_buffer.len() < encoding::PRESCAN_LIMIT && !last {
+                return;
+            }
+            self.prescanned = true;
+
+            if let Some(encoding) = encoding::sniff_declared(&self.sniff_buffer, transport_charset)
+            {
+                return self.start_decoding(encoding);
+            }
+        }
+
+        // ASCII reads the same in all the encodings that can be guessed.
+        let ascii = self
+            .sniff_buffer
+            .iter()
+            .position(|b| !b.is_ascii())
+            .unwrap_or(self.sniff_buffer.len());
+        if ascii > 0 {
+            let text = std::str::from_utf8(&self.sniff_buffer[..ascii]).unwrap();
+            self.parser.process(StrTendril::from(text));
+            self.sniff_buffer.drain(..ascii);
+        }
+
+        if let Some(encoding) = encoding::guess(&self.sniff_buffer, last) {
+            self.start_decoding(encoding);
+        }
+    }
+
+    fn start_decoding(&mut self, encoding: &'static Encoding) {
+        self.decoder = Some(encoding.new_decoder_with_bom_removal());
+
+        let buffered = mem::take(&mut self.sniff_buffer);
+        self.decode(&buffered, false);
+    }
+
+    fn decode(&mut self, bytes: &[u8], last: bool) {
+        let decoder = match self.decoder {
+            Some(ref mut decoder) => decoder,
+            None => return,
+        };
+
+        let capacity = decoder
+            .max_utf8_buffer_length(bytes.len())
+            .expect("chunk too large to decode");
+        let mut text = String::with_capacity(capacity);
+        let _ = decoder.decode_to_string(bytes, &mut text, last);
+
+        if text.is_empty() {
+            return;
+        }
+
+        self.parser.process(StrTendril::from(text));
+    }
+}
+
+/// Feeds the tokenizer like html5ever's `Parser`, with a `PositionSink`
+/// between it and the tree builder.
+pub(crate) struct HtmlParser {
+    tokenizer: Tokenizer<PositionSink>,
+    input: BufferQueue,
+}
+
+impl HtmlParser {
+    fn new(
+        builder: TreeBuilder<NodeId, Document>,
+        opts: TokenizerOpts,
+        track_positions: bool,
+    ) -> Self {
+        Self {
+            tokenizer: Tokenizer::new(PositionSink::new(builder, track_positions), opts),
+            input: BufferQueue::new(),
+        }
+    }
+
+    fn run(&mut self) {
+        // Scripts are never executed, so parsing just goes on.
+        while let TokenizerResult::Script(_) = self.tokenizer.feed(&mut self.input) {}
+    }
+}
+
+impl TendrilSink<tendril::fmt::UTF8> for HtmlParser {
+    fn process(&mut self, t: StrTendril) {
+        if !self.tokenizer.sink.tracks_positions() {
+            self.input.push_back(t);
+            self.run();
+            return;
+        }
+
+        for (i, c) in t.char_indices() {
+            self.tokenizer.sink.feeding(c);
+            self.input
+                .push_back(t.subtendril(i as u32, c.len_utf8() as u32));
+            self.run();
+            self.tokenizer.sink.fed(c);
+        }
+    }
+
+    fn error(&mut self, desc: Cow<'static, str>) {
+        self.tokenizer.sink.builder.sink.parse_error(desc);
+    }
+
+    type Output = Document;
+
+    fn finish(mut self) -> Document {
+        self.run();
+        self.tokenizer.sink.ending();
+        self.tokenizer.end();
+        self.tokenizer.sink.builder.sink.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parser_decodes_at_once_when_encoding_is_settled() {
+        let mut parser = ParseOptions::new().parser_with_charset(Some("text/html; charset=utf-8"));
+        parser.feed(b"<p>a");
+        assert_eq!(
+            parser.decoder.as_ref().unwrap().encoding(),
+            encoding_rs::UTF_8
+        );
+
+        let mut parser = ParseOptions::new().parser();
+        parser.feed(b"\xFF");
+        assert!(parser.decoder.is_none());
+        parser.feed(b"\xFE<p>a");
+        assert_eq!(
+            parser.decoder.as_ref().unwrap().encoding(),
+            encoding_rs::UTF_16LE
+        );
+
+        let mut parser = ParseOptions::new().parser();
+        parser.feed(b"<p>a");
+        assert!(parser.decoder.is_none());
+    }
+}
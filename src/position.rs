@@ -0,0 +1,208 @@
+use crate::dom_tree::NodeId;
+use crate::Document;
+use html5ever::tokenizer::{
+    ParseError as ParseErrorToken, StartTag, Tag, TagToken, Token, TokenSink, TokenSinkResult,
+};
+use html5ever::tree_builder::TreeBuilder;
+use std::borrow::Cow;
+use std::fmt;
+
+/// Where a node starts in the parsed source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SourcePosition {
+    /// Byte offset of the start tag's `<`.
+    pub offset: usize,
+
+    /// Line number, starting at 1.
+    pub line: u64,
+
+    /// Column number in characters, starting at 1.
+    pub column: u64,
+}
+
+/// An error that occurred during parsing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// The error message reported by html5ever.
+    pub message: Cow<'static, str>,
+
+    /// The line the error occurred on, starting at 1.
+    pub line: u64,
+
+    /// Byte offset of the error, only known when positions are tracked with
+    /// `ParseOptions::track_positions`. Errors found by the tokenizer are
+    /// where it found them, and the others at the start of the token being
+    /// processed, like the `<` of a misplaced tag.
+    pub offset: Option<usize>,
+
+    /// Column in characters, starting at 1. Known along with `offset`.
+    pub column: Option<u64>,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.column {
+            Some(column) => write!(f, "line {}, column {}: {}", self.line, column, self.message),
+            None => write!(f, "line {}: {}", self.line, self.message),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Sits between the tokenizer and the tree builder, telling the document
+/// where each token starts so that elements get the position of their start
+/// tag.
+///
+/// While positions are tracked, the tokenizer is fed a character at a time,
+/// so that each token is seen as soon as its last character is. A tag,
+/// comment or doctype starts at the `<` fed when no markup was pending, or
+/// while the previous token was emitted: a `<` inside an attribute value or
+/// a comment emits nothing, while one which turns out to be text is emitted
+/// once the character after it is fed.
+pub(crate) struct PositionSink {
+    pub(crate) builder: TreeBuilder<NodeId, Document>,
+    cursor: Option<Cursor>,
+}
+
+/// Where the tokenizer is in the source.
+struct Cursor {
+    /// The character being fed, and the one after it.
+    current: SourcePosition,
+    next: SourcePosition,
+    previous: char,
+
+    /// The `<` starting the markup being tokenized.
+    markup_start: Option<SourcePosition>,
+
+    /// Whether a token was emitted while the current character was fed.
+    emitted: bool,
+}
+
+impl PositionSink {
+    pub fn new(builder: TreeBuilder<NodeId, Document>, track_positions: bool) -> Self {
+        let start = SourcePosition {
+            offset: 0,
+            line: 1,
+            column: 1,
+        };
+
+        Self {
+            builder,
+            cursor: if track_positions {
+                Some(Cursor {
+                    current: start,
+                    next: start,
+                    previous: '\0',
+                    markup_start: None,
+                    emitted: false,
+                })
+            } else {
+                None
+            },
+        }
+    }
+
+    pub fn tracks_positions(&self) -> bool {
+        self.cursor.is_some()
+    }
+
+    /// Called before the tokenizer is fed `c`.
+    pub fn feeding(&mut self, c: char) {
+        if let Some(ref mut cursor) = self.cursor {
+            cursor.current = cursor.next;
+            cursor.emitted = false;
+
+            let next = &mut cursor.next;
+            next.offset += c.len_utf8();
+            if c == '\n' && cursor.previous == '\r' {
+                // The end of a CRLF line break.
+            } else if c == '\n' || c == '\r' {
+                next.line += 1;
+                next.column = 1;
+            } else {
+                next.column += 1;
+            }
+        }
+    }
+
+    /// Called after the tokenizer was fed `c`.
+    pub fn fed(&mut self, c: char) {
+        if let Some(ref mut cursor) = self.cursor {
+            match c {
+                '<' if cursor.emitted || cursor.markup_start.is_none() => {
+                    cursor.markup_start = Some(cursor.current);
+                }
+                // `</>` is dropped without emitting anything.
+                '>' if cursor.previous == '/'
+                    && cursor.markup_start.map(|start| start.offset + 2)
+                        == Some(cursor.current.offset) =>
+                {
+                    cursor.markup_start = None;
+                }
+                _ if cursor.emitted => cursor.markup_start = None,
+                _ => (),
+            }
+
+            cursor.previous = c;
+        }
+    }
+
+    /// Called before the tokenizer is told the input ended.
+    pub fn ending(&mut self) {
+        if let Some(ref mut cursor) = self.cursor {
+            cursor.current = cursor.next;
+        }
+    }
+}
+
+impl TokenSink for PositionSink {
+    type Handle = NodeId;
+
+    fn process_token(&mut self, token: Token, line_number: u64) -> TokenSinkResult<NodeId> {
+        let cursor = match self.cursor {
+            Some(ref mut cursor) => cursor,
+            None => return self.builder.process_token(token, line_number),
+        };
+
+        let position = match token {
+            ParseErrorToken(_) => cursor.current,
+            _ => {
+                cursor.emitted = true;
+                cursor.markup_start.unwrap_or(cursor.current)
+            }
+        };
+        let start_tag = match token {
+            TagToken(Tag {
+                kind: StartTag,
+                ref name,
+                ..
+            }) => Some(name.clone()),
+            _ => None,
+        };
+
+        self.builder.sink.position = Some(position);
+        self.builder.sink.created = None;
+        let result = self.builder.process_token(token, line_number);
+
+        // The tree builder creates the element of a start tag after any it
+        // implies, like a missing `<tbody>`, which get no position.
+        if let (Some(name), Some(id)) = (start_tag, self.builder.sink.created.take()) {
+            let tree = &self.builder.sink.tree;
+            if tree.get_name(&id).local.eq_ignore_ascii_case(&name) {
+                tree.update_node(&id, |node| node.position = Some(position));
+            }
+        }
+
+        result
+    }
+
+    fn end(&mut self) {
+        self.builder.end();
+    }
+
+    fn adjusted_current_node_present_but_not_in_html_namespace(&self) -> bool {
+        self.builder
+            .adjusted_current_node_present_but_not_in_html_namespace()
+    }
+}
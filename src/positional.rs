@@ -0,0 +1,316 @@
+//! jQuery's positional pseudo-classes, like `li:eq(2)` or `tr:gt(0)`.
+//!
+//! They depend on the set of matched elements rather than on the tree, so a
+//! selector using them is split into steps at each compound selector holding
+//! one. Each step is matched relative to the elements the previous step
+//! matched, and the positional pseudo-classes then filter what it matched by
+//! index. `ul li:first a` finds the first `li` inside any `ul`, then the `a`
+//! elements inside that `li`.
+
+use crate::dom_tree::{sort_in_document_order, Node};
+use crate::matcher::{
+    is_scoped, MatchScope, Matcher, Matches, RelativeSelector, ScopedSelector, SelectorError,
+};
+use cssparser::{ParserInput, Token};
+use markup5ever::{Namespace, Prefix};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::mem;
+
+/// A positional pseudo-class. Indexes start at 0 and negative ones count from
+/// the end of the set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Position {
+    Eq(i32),
+    Gt(i32),
+    Lt(i32),
+    First,
+    Last,
+    Even,
+    Odd,
+}
+
+impl Position {
+    fn keeps(self, index: usize, len: usize) -> bool {
+        let resolve = |n: i32| {
+            if n < 0 {
+                len as i64 + n as i64
+            } else {
+                n as i64
+            }
+        };
+        let i = index as i64;
+
+        match self {
+            Position::Eq(n) => i == resolve(n),
+            Position::Gt(n) => i > resolve(n),
+            Position::Lt(n) => i < resolve(n),
+            Position::First => index == 0,
+            Position::Last => index + 1 == len,
+            Position::Even => !Position::Odd.keeps(index, len),
+            Position::Odd => index % 2 == 1,
+        }
+    }
+
+    fn filter<T>(self, items: Vec<T>) -> Vec<T> {
+        let len = items.len();
+        items
+            .into_iter()
+            .enumerate()
+            .filter(|(i, _)| self.keeps(*i, len))
+            .map(|(_, item)| item)
+            .collect()
+    }
+
+    pub(crate) fn to_css<W: fmt::Write>(self, dest: &mut W) -> fmt::Result {
+        match self {
+            Position::Eq(n) => write!(dest, ":eq({})", n),
+            Position::Gt(n) => write!(dest, ":gt({})", n),
+            Position::Lt(n) => write!(dest, ":lt({})", n),
+            Position::First => dest.write_str(":first"),
+            Position::Last => dest.write_str(":last"),
+            Position::Even => dest.write_str(":even"),
+            Position::Odd => dest.write_str(":odd"),
+        }
+    }
+}
+
+/// A selector using positional pseudo-classes.
+///
+/// In a selector relative to each root, like `> li:first`, the first step is
+/// relative to the roots too.
+pub(crate) struct PositionalSelector {
+    /// The comma separated selectors.
+    groups: Vec<Vec<Step>>,
+}
+
+struct Step {
+    /// The first step is matched like a whole selector, the later ones
+    /// relative to each element the previous step matched.
+    selector: StepSelector,
+    positions: Vec<Position>,
+}
+
+enum StepSelector {
+    First(Matcher),
+    Relative(RelativeSelector),
+}
+
+impl PositionalSelector {
+    /// Parses `sel`, or returns `None` if it has no positional pseudo-classes
+    /// outside of functional ones like `:not()`, which cannot be evaluated
+    /// this way and fail to parse as a plain selector. If `scoped`, `sel` may
+    /// be relative to the roots like the ones `ScopedSelector` parses.
+    pub(crate) fn parse(
+        sel: &str,
+        namespaces: &HashMap<Prefix, Namespace>,
+        scoped: bool,
+    ) -> Result<Option<Self>, SelectorError> {
+        let scoped = scoped && is_scoped(sel);
+
+        // Reports errors against the whole selector.
+        if scoped {
+            ScopedSelector::with_positional(sel, namespaces)?;
+        } else {
+            Matcher::with_positional(sel, namespaces)?;
+        }
+
+        let groups = split(sel);
+        if groups
+            .iter()
+            .all(|steps| steps.iter().all(|(_, positions)| positions.is_empty()))
+        {
+            return Ok(None);
+        }
+
+        let mut compiled = vec![];
+        for steps in groups {
+            let mut group = vec![];
+            for (i, (source, positions)) in steps.into_iter().enumerate() {
+                let selector = if i == 0 && !scoped {
+                    StepSelector::First(Matcher::with_namespaces(&source, namespaces)?)
+                } else {
+                    StepSelector::Relative(RelativeSelector::parse(source.trim(), namespaces)?)
+                };
+                group.push(Step {
+                    selector,
+                    positions,
+                });
+            }
+            compiled.push(group);
+        }
+
+        Ok(Some(PositionalSelector { groups: compiled }))
+    }
+
+    /// Selects from `roots`, the way `Matches` does with `match_scope`, in
+    /// document order.
+    pub(crate) fn select<'a>(&self, roots: &[Node<'a>], match_scope: MatchScope) -> Vec<Node<'a>> {
+        let mut selected = vec![];
+        let mut seen = HashSet::new();
+
+        for steps in &self.groups {
+            // What a first step relative to the roots is relative to.
+            let mut nodes: Vec<Node<'a>> = roots.to_vec();
+
+            for step in steps {
+                nodes = match step.selector {
+                    StepSelector::First(ref matcher) => Matches::from_list(
+                        roots.iter().cloned(),
+                        matcher.clone(),
+                        match_scope.clone(),
+                    )
+                    .collect(),
+                    StepSelector::Relative(ref selector) => {
+                        let mut found = vec![];
+                        let mut ids = HashSet::new();
+                        for node in &nodes {
+                            for node in selector.find(node, node.tree.quirks_mode(), false) {
+                                if ids.insert(node.id) {
+                                    found.push(node);
+                                }
+                            }
+                        }
+                        found
+                    }
+                };
+
+                // The positions index the elements in document order.
+                sort_in_document_order(&mut nodes);
+                for position in &step.positions {
+                    nodes = position.filter(nodes);
+                }
+            }
+
+            for node in nodes {
+                if seen.insert(node.id) {
+                    selected.push(node);
+                }
+            }
+        }
+
+        sort_in_document_order(&mut selected);
+        selected
+    }
+}
+
+/// Splits `sel` into comma separated groups of steps, each with the
+/// positional pseudo-classes it ends with removed from its source.
+fn split(sel: &str) -> Vec<Vec<(String, Vec<Position>)>> {
+    let mut input = ParserInput::new(sel);
+    let mut parser = cssparser::Parser::new(&mut input);
+
+    let mut groups = vec![];
+    let mut steps = vec![];
+    let mut source = String::new();
+    let mut positions = vec![];
+
+    // The start of the source not copied yet.
+    let mut copied = parser.position();
+    let mut after_colon = false;
+
+    loop {
+        let start = parser.position();
+        let token = match parser.next_including_whitespace() {
+            Ok(token) => token.clone(),
+            Err(_) => break,
+        };
+
+        match token {
+            // Not `::first`, which would be a pseudo-element.
+            Token::Colon if !after_colon => {
+                let state = parser.state();
+                match parse_position(&mut parser) {
+                    Some(position) => {
+                        source.push_str(parser.slice(copied..start));
+                        copied = parser.position();
+                        positions.push(position);
+                        continue;
+                    }
+                    None => parser.reset(&state),
+                }
+            }
+            // Whitespace ending a group separates nothing, and would make an
+            // empty step of `li:first `.
+            Token::WhiteSpace(_) if at_group_end(&mut parser) => {
+                source.push_str(parser.slice(copied..start));
+                copied = parser.position();
+            }
+            Token::WhiteSpace(_) | Token::Delim('>') | Token::Delim('+') | Token::Delim('~')
+                if !positions.is_empty() =>
+            {
+                source.push_str(parser.slice(copied..start));
+                copied = start;
+                steps.push(finish_step(&mut source, &mut positions));
+            }
+            Token::Comma => {
+                source.push_str(parser.slice(copied..start));
+                copied = parser.position();
+                steps.push(finish_step(&mut source, &mut positions));
+                groups.push(mem::take(&mut steps));
+            }
+            _ => (),
+        }
+
+        after_colon = token == Token::Colon;
+    }
+
+    source.push_str(parser.slice_from(copied));
+    steps.push(finish_step(&mut source, &mut positions));
+    groups.push(steps);
+
+    groups
+}
+
+/// Whether the next token after any whitespace is a comma or the end of the
+/// selector.
+fn at_group_end(parser: &mut cssparser::Parser) -> bool {
+    let state = parser.state();
+    let end = matches!(parser.next(), Err(_) | Ok(Token::Comma));
+    parser.reset(&state);
+    end
+}
+
+/// Parses the positional pseudo-class after a colon.
+fn parse_position(parser: &mut cssparser::Parser) -> Option<Position> {
+    let position = match parser.next_including_whitespace().ok()?.clone() {
+        Token::Ident(name) => match &*name.to_ascii_lowercase() {
+            "first" => Position::First,
+            "last" => Position::Last,
+            "even" => Position::Even,
+            "odd" => Position::Odd,
+            _ => return None,
+        },
+        Token::Function(name) => {
+            let make = match &*name.to_ascii_lowercase() {
+                "eq" => Position::Eq,
+                "gt" => Position::Gt,
+                "lt" => Position::Lt,
+                _ => return None,
+            };
+            let n = parser
+                .parse_nested_block(|input| {
+                    input
+                        .expect_integer()
+                        .map_err(cssparser::ParseError::<()>::from)
+                })
+                .ok()?;
+            make(n)
+        }
+        _ => return None,
+    };
+
+    Some(position)
+}
+
+fn finish_step(source: &mut String, positions: &mut Vec<Position>) -> (String, Vec<Position>) {
+    // The compound selector holding the positional pseudo-classes was empty,
+    // like in `li > :first`.
+    if source.trim().is_empty()
+        || source.ends_with(|c: char| c.is_whitespace() || "+>~".contains(c))
+    {
+        source.push('*');
+    }
+
+    (mem::take(source), mem::take(positions))
+}
@@ -1,5 +1,6 @@
 use crate::Document;
 use crate::Selection;
+use crate::SerializeOptions;
 use tendril::StrTendril;
 
 impl Document {
@@ -9,6 +10,21 @@ impl Document {
         self.tree.root().html()
     }
 
+    /// Gets the HTML contents of the document, formatted according to `options`.
+    pub fn html_with(&self, options: &SerializeOptions) -> StrTendril {
+        self.tree.root().html_with(options)
+    }
+
+    /// Gets the contents of the document as well-formed XML. See `Node::xml`.
+    pub fn xml(&self) -> StrTendril {
+        self.tree.root().xml()
+    }
+
+    /// Gets the contents of the document as polyglot XHTML. See `Node::xhtml`.
+    pub fn xhtml(&self) -> StrTendril {
+        self.tree.root().xhtml()
+    }
+
     /// Gets the text content of the document.
     pub fn text(&self) -> StrTendril {
         self.tree.root().text()
@@ -89,6 +105,51 @@ impl<'a> Selection<'a> {
         StrTendril::new()
     }
 
+    /// Gets the HTML contents of the children of the first element in the set
+    /// of matched elements, like jQuery's `.html()`.
+    pub fn inner_html(&self) -> StrTendril {
+        match self.nodes().first() {
+            Some(node) => node.inner_html(),
+            None => StrTendril::new(),
+        }
+    }
+
+    /// Gets the HTML of the first element in the set of matched elements,
+    /// including the element itself. Is the same as `html`.
+    pub fn outer_html(&self) -> StrTendril {
+        match self.nodes().first() {
+            Some(node) => node.outer_html(),
+            None => StrTendril::new(),
+        }
+    }
+
+    /// Gets the HTML contents of the first element in the set of matched
+    /// elements, formatted according to `options`.
+    pub fn html_with(&self, options: &SerializeOptions) -> StrTendril {
+        match self.nodes().first() {
+            Some(node) => node.html_with(options),
+            None => StrTendril::new(),
+        }
+    }
+
+    /// Gets the first element in the set of matched elements as well-formed
+    /// XML. See `Node::xml`.
+    pub fn xml(&self) -> StrTendril {
+        match self.nodes().first() {
+            Some(node) => node.xml(),
+            None => StrTendril::new(),
+        }
+    }
+
+    /// Gets the first element in the set of matched elements as polyglot
+    /// XHTML. See `Node::xhtml`.
+    pub fn xhtml(&self) -> StrTendril {
+        match self.nodes().first() {
+            Some(node) => node.xhtml(),
+            None => StrTendril::new(),
+        }
+    }
+
     /// Gets the combined text content of each element in the set of matched
     /// elements, including their descendants.
     pub fn text(&self) -> StrTendril {
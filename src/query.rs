@@ -1,4 +1,5 @@
-use crate::matcher::Matcher;
+use crate::matcher::{MatchScope, Matcher, Matches};
+use crate::Node;
 use crate::Selection;
 use std::collections::HashSet;
 
@@ -7,7 +8,8 @@ impl<'a> Selection<'a> {
     /// returns true if at least one of these elements matches.
     pub fn is(&self, sel: &str) -> bool {
         if self.length() > 0 {
-            return Matcher::new(sel)
+            return self
+                .matcher(sel)
                 .map(|matcher| self.is_matcher(&matcher))
                 .unwrap_or(false);
         }
@@ -22,7 +24,7 @@ impl<'a> Selection<'a> {
             return self
                 .nodes()
                 .into_iter()
-                .filter(|node| matcher.match_element(*node))
+                .filter(|node| matcher.match_element(node))
                 .count()
                 > 0;
         }
@@ -50,4 +52,189 @@ impl<'a> Selection<'a> {
 
         false
     }
+
+    /// Reduces the set of matched elements to those matching the selector.
+    /// It returns a new Selection object, and an empty one if the selector
+    /// is invalid.
+    pub fn filter(&self, sel: &str) -> Selection<'a> {
+        match self.matcher(sel) {
+            Ok(matcher) => self.filter_matcher(&matcher),
+            Err(_) => Selection::default(),
+        }
+    }
+
+    /// Reduces the set of matched elements to those matching the matcher.
+    pub fn filter_matcher(&self, matcher: &Matcher) -> Selection<'a> {
+        self.filter_by(|node| node.is_element() && matcher.match_element(node))
+    }
+
+    /// Reduces the set of matched elements to those which are also in `sel`.
+    pub fn filter_selection(&self, sel: &Selection) -> Selection<'a> {
+        let ids = sel.ids();
+        self.filter_by(|node| ids.contains(&node.id))
+    }
+
+    /// Reduces the set of matched elements to those for which `f` returns
+    /// true. `f` is called with the index and a selection of each element.
+    pub fn filter_fn<F>(&self, mut f: F) -> Selection<'a>
+    where
+        F: FnMut(usize, &Selection<'a>) -> bool,
+    {
+        let mut index = 0;
+        self.filter_by(|node| {
+            index += 1;
+            f(index - 1, &Selection::from(node.clone()))
+        })
+    }
+
+    /// Removes the elements matching the selector from the set of matched
+    /// elements. An invalid selector matches nothing, so no element is
+    /// removed.
+    pub fn not(&self, sel: &str) -> Selection<'a> {
+        match self.matcher(sel) {
+            Ok(matcher) => self.not_matcher(&matcher),
+            Err(_) => self.filter_by(|_| true),
+        }
+    }
+
+    /// Removes the elements matching the matcher from the set of matched
+    /// elements.
+    pub fn not_matcher(&self, matcher: &Matcher) -> Selection<'a> {
+        self.filter_by(|node| !(node.is_element() && matcher.match_element(node)))
+    }
+
+    /// Removes the elements which are in `sel` from the set of matched
+    /// elements.
+    pub fn not_selection(&self, sel: &Selection) -> Selection<'a> {
+        let ids = sel.ids();
+        self.filter_by(|node| !ids.contains(&node.id))
+    }
+
+    /// Removes the elements for which `f` returns true from the set of
+    /// matched elements. `f` is called with the index and a selection of
+    /// each element.
+    pub fn not_fn<F>(&self, mut f: F) -> Selection<'a>
+    where
+        F: FnMut(usize, &Selection<'a>) -> bool,
+    {
+        self.filter_fn(|index, sel| !f(index, sel))
+    }
+
+    /// Reduces the set of matched elements to those which have a descendant
+    /// matching the selector. It returns a new Selection object, and an
+    /// empty one if the selector is invalid.
+    pub fn has(&self, sel: &str) -> Selection<'a> {
+        match self.matcher(sel) {
+            Ok(matcher) => self.has_matcher(&matcher),
+            Err(_) => Selection::default(),
+        }
+    }
+
+    /// Reduces the set of matched elements to those which have a descendant
+    /// matching the matcher.
+    pub fn has_matcher(&self, matcher: &Matcher) -> Selection<'a> {
+        self.filter_by(|node| {
+            Matches::from_one(node.clone(), matcher.clone(), MatchScope::ChildrenOnly)
+                .next()
+                .is_some()
+        })
+    }
+
+    /// Reduces the set of matched elements to those which have a descendant
+    /// in `sel`.
+    pub fn has_selection(&self, sel: &Selection) -> Selection<'a> {
+        let mut ancestors = HashSet::new();
+        for node in sel.nodes() {
+            let mut parent = node.parent();
+            while let Some(node) = parent {
+                // The ancestors of an ancestor already seen are there too.
+                if !ancestors.insert(node.id) {
+                    break;
+                }
+                parent = node.parent();
+            }
+        }
+
+        self.filter_by(|node| ancestors.contains(&node.id))
+    }
+
+    /// Adds the elements of the document matching the selector to the set
+    /// of matched elements. It returns a new Selection object in document
+    /// order, with each element listed once. An invalid selector matches
+    /// nothing, so no element is added.
+    ///
+    /// The document is the one of the matched elements, so unlike in jQuery,
+    /// nothing is added to an empty selection. Use `union` with the result of
+    /// `Document::select` instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use nipper::Document;
+    ///
+    /// let document = Document::from("<p>One</p><p>Two</p><span>Three</span>");
+    /// assert_eq!(document.select("span").add("p").length(), 3);
+    /// assert_eq!(document.select("em").add("p").length(), 0);
+    /// assert_eq!(document.select("em").union(&document.select("p")).length(), 2);
+    /// ```
+    pub fn add(&self, sel: &str) -> Selection<'a> {
+        match self.matcher(sel) {
+            Ok(matcher) => self.add_matcher(&matcher),
+            Err(_) => self.union(&Selection::default()),
+        }
+    }
+
+    /// Adds the elements of the document matching the matcher to the set of
+    /// matched elements. Like `add`, it adds nothing to an empty selection.
+    pub fn add_matcher(&self, matcher: &Matcher) -> Selection<'a> {
+        let found = match self.nodes.first() {
+            Some(node) => Selection {
+                nodes: Matches::from_one(
+                    node.tree.root(),
+                    matcher.clone(),
+                    MatchScope::IncludeNode,
+                )
+                .collect(),
+            },
+            None => Selection::default(),
+        };
+
+        self.union(&found)
+    }
+
+    /// Adds the elements of `sel` to the set of matched elements. It is an
+    /// alias for `union`.
+    pub fn add_selection(&self, sel: &Selection<'a>) -> Selection<'a> {
+        self.union(sel)
+    }
+
+    /// Gets the elements which are both in the set of matched elements and
+    /// in `sel`. It is an alias for `filter_selection`.
+    pub fn intersection(&self, sel: &Selection) -> Selection<'a> {
+        self.filter_selection(sel)
+    }
+
+    /// Gets the elements which are in the set of matched elements or in
+    /// `sel`. It returns a new Selection object in document order, with each
+    /// element listed once.
+    pub fn union(&self, sel: &Selection<'a>) -> Selection<'a> {
+        let nodes = self.nodes().iter().chain(sel.nodes()).cloned().collect();
+        Selection::in_document_order(nodes)
+    }
+
+    /// Keeps the elements passing `f`, each listed once, in document order.
+    fn filter_by<F>(&self, mut f: F) -> Selection<'a>
+    where
+        F: FnMut(&Node<'a>) -> bool,
+    {
+        let mut set = HashSet::with_capacity(self.length());
+        let nodes = self
+            .nodes()
+            .iter()
+            .filter(|node| set.insert(node.id) && f(node))
+            .cloned()
+            .collect();
+
+        Selection::in_document_order(nodes)
+    }
 }
@@ -1,8 +1,15 @@
-use crate::dom_tree::Node;
+use crate::dom_tree::{sort_in_document_order, Node, NodeId};
+use crate::matcher::{Matcher, ScopedSelector, SelectorError};
+use crate::positional::PositionalSelector;
+use markup5ever::{Namespace, Prefix};
+use std::collections::{HashMap, HashSet};
 
 /// Selection represents a collection of nodes matching some criteria. The
 /// initial Selection object can be created by using [`Document::select`], and then
 /// manipulated using methods itself.
+///
+/// The selections returned by traversal and set operations list their
+/// elements in document order, each once, like the DOM does.
 #[derive(Debug, Clone)]
 pub struct Selection<'a> {
     pub(crate) nodes: Vec<Node<'a>>,
@@ -19,3 +26,56 @@ impl<'a> From<Node<'a>> for Selection<'a> {
         Self { nodes: vec![node] }
     }
 }
+
+impl<'a> Selection<'a> {
+    /// Calls `f` with the namespace prefixes used in the document.
+    fn with_namespaces<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&HashMap<Prefix, Namespace>) -> R,
+    {
+        match self.nodes.first() {
+            Some(node) => f(node.tree.namespaces()),
+            None => f(&HashMap::new()),
+        }
+    }
+
+    /// Creates a selection of `nodes` in document order, each listed once.
+    pub(crate) fn in_document_order(nodes: Vec<Node<'a>>) -> Selection<'a> {
+        let mut set = HashSet::with_capacity(nodes.len());
+        let mut nodes: Vec<Node<'a>> = nodes
+            .into_iter()
+            .filter(|node| set.insert(node.id))
+            .collect();
+        sort_in_document_order(&mut nodes);
+
+        Selection { nodes }
+    }
+
+    /// The ids of the nodes in the selection.
+    pub(crate) fn ids(&self) -> HashSet<NodeId> {
+        self.nodes.iter().map(|node| node.id).collect()
+    }
+
+    /// Creates a matcher for `sel` which resolves the namespace prefixes used
+    /// in the document.
+    pub(crate) fn matcher(&self, sel: &str) -> Result<Matcher, SelectorError> {
+        self.with_namespaces(|namespaces| Matcher::with_namespaces(sel, namespaces))
+    }
+
+    /// Parses `sel` if it uses positional pseudo-classes like `:eq(2)`.
+    pub(crate) fn positional_selector(
+        &self,
+        sel: &str,
+    ) -> Result<Option<PositionalSelector>, SelectorError> {
+        self.with_namespaces(|namespaces| PositionalSelector::parse(sel, namespaces, true))
+    }
+
+    /// Parses `sel` if it is relative to the elements of the selection, like
+    /// `> li` or `:scope > li`.
+    pub(crate) fn scoped_selector(
+        &self,
+        sel: &str,
+    ) -> Result<Option<ScopedSelector>, SelectorError> {
+        self.with_namespaces(|namespaces| ScopedSelector::parse(sel, namespaces))
+    }
+}
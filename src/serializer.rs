@@ -0,0 +1,869 @@
+use crate::dom_tree::{Node, SerializableNodeRef};
+use html5ever::serialize::{serialize, SerializeOpts};
+use markup5ever::serialize::{AttrRef, Serialize, Serializer, TraversalScope};
+use markup5ever::{local_name, namespace_url, ns};
+use markup5ever::{LocalName, Namespace, Prefix, QualName};
+use std::io::{self, Write};
+use tendril::StrTendril;
+
+/// Options for serializing nodes into HTML, see `Node::html_with`.
+///
+/// By default the markup is serialized exactly as parsed, like `Node::html`
+/// does. Pretty printing puts block elements on their own lines, indented by
+/// depth, while minifying removes everything that does not change how the
+/// markup is rendered. Either way the contents of `<pre>`, `<textarea>`,
+/// `<script>` and `<style>` are kept as they are.
+///
+/// # Examples
+///
+/// ```
+/// use nipper::{Document, SerializeOptions};
+///
+/// let document = Document::from("<ul>\n  <li>One</li>  <li><b>Two</b>  words</li></ul>");
+/// let ul = document.select("ul");
+///
+/// assert_eq!(
+///     ul.html_with(&SerializeOptions::pretty()).as_ref(),
+///     "<ul>\n  <li>One</li>\n  <li><b>Two</b> words</li>\n</ul>"
+/// );
+/// assert_eq!(
+///     ul.html_with(&SerializeOptions::minified()).as_ref(),
+///     "<ul><li>One</li><li><b>Two</b> words</li></ul>"
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct SerializeOptions {
+    format: Format,
+    indent: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Preserve,
+    Pretty,
+    Minify,
+}
+
+impl Default for SerializeOptions {
+    fn default() -> Self {
+        Self {
+            format: Format::Preserve,
+            indent: "  ".to_string(),
+        }
+    }
+}
+
+impl SerializeOptions {
+    /// Creates options which serialize the markup as parsed.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates options for pretty printing, indenting by two spaces.
+    pub fn pretty() -> Self {
+        Self {
+            format: Format::Pretty,
+            ..Self::default()
+        }
+    }
+
+    /// Creates options for minifying: whitespace is collapsed and dropped
+    /// between blocks, comments are dropped, and attribute values are only
+    /// quoted where needed.
+    pub fn minified() -> Self {
+        Self {
+            format: Format::Minify,
+            ..Self::default()
+        }
+    }
+
+    /// Sets the string each level is indented by when pretty printing.
+    pub fn indent(mut self, indent: &str) -> Self {
+        self.indent = indent.to_string();
+        self
+    }
+
+    pub(crate) fn serialize(&self, node: &Node, traversal_scope: TraversalScope) -> StrTendril {
+        let inner: SerializableNodeRef = node.clone().into();
+
+        if self.format == Format::Preserve {
+            let mut result = vec![];
+            serialize(
+                &mut result,
+                &inner,
+                SerializeOpts {
+                    scripting_enabled: true,
+                    traversal_scope,
+                    create_missing_parent: false,
+                },
+            )
+            .unwrap();
+            return StrTendril::try_from_byte_slice(&result).unwrap();
+        }
+
+        let mut builder = ItemBuilder::default();
+        inner.serialize(&mut builder, traversal_scope).unwrap();
+
+        let mut formatter = Formatter {
+            options: self,
+            out: String::new(),
+        };
+        match self.format {
+            Format::Pretty => formatter.pretty_block(&builder.items, 0),
+            _ => formatter.minify_children(&builder.items, None),
+        }
+        StrTendril::from(formatter.out)
+    }
+}
+
+/// Serializes nodes into well-formed XML.
+///
+/// Namespace declarations are generated for the namespaces of element and
+/// attribute names wherever they are not in scope yet, and empty elements are
+/// closed with `/>`. In XHTML mode only void elements like `<br />` are
+/// self-closed, and the contents of `<script>` and `<style>` are not escaped
+/// but put in a commented out CDATA section when needed, so that the output
+/// can be served as both HTML and XML.
+pub(crate) struct XmlSerializer<W: Write> {
+    writer: W,
+    xhtml: bool,
+
+    /// The namespaces declared by each open element.
+    scopes: Vec<Vec<(Option<Prefix>, Namespace)>>,
+
+    /// For each open element whose text is not escaped in XHTML mode, the
+    /// CDATA markers commented out in its language.
+    raw_text: Vec<Option<(&'static str, &'static str)>>,
+
+    /// Whether the start tag of the current element still misses its `>`.
+    start_tag_open: bool,
+}
+
+impl<W: Write> XmlSerializer<W> {
+    pub fn new(writer: W, xhtml: bool) -> Self {
+        Self {
+            writer,
+            xhtml,
+            scopes: vec![],
+            raw_text: vec![],
+            start_tag_open: false,
+        }
+    }
+
+    fn namespace_in_scope(&self, prefix: &Option<Prefix>) -> Namespace {
+        for scope in self.scopes.iter().rev() {
+            if let Some((_, ns)) = scope.iter().find(|(p, _)| p == prefix) {
+                return ns.clone();
+            }
+        }
+
+        ns!()
+    }
+
+    fn close_start_tag(&mut self) -> io::Result<()> {
+        if self.start_tag_open {
+            self.start_tag_open = false;
+            self.writer.write_all(b">")?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes a doctype with its public and system ids, if any, like
+    /// `<!DOCTYPE html PUBLIC "-//W3C//DTD XHTML 1.0 Strict//EN" "...">`.
+    pub fn write_doctype_with_ids(
+        &mut self,
+        name: &str,
+        public_id: &str,
+        system_id: &str,
+    ) -> io::Result<()> {
+        self.close_start_tag()?;
+        self.writer.write_all(b"<!DOCTYPE ")?;
+        self.writer.write_all(name.as_bytes())?;
+
+        if !public_id.is_empty() {
+            self.writer.write_all(b" PUBLIC ")?;
+            self.write_quoted_id(public_id)?;
+        } else if !system_id.is_empty() {
+            self.writer.write_all(b" SYSTEM")?;
+        }
+        if !system_id.is_empty() {
+            self.writer.write_all(b" ")?;
+            self.write_quoted_id(system_id)?;
+        }
+
+        self.writer.write_all(b">")
+    }
+
+    /// Ids cannot be escaped, but can hold the quote they are not in.
+    fn write_quoted_id(&mut self, id: &str) -> io::Result<()> {
+        let quote: &[u8] = if id.contains('"') { b"'" } else { b"\"" };
+        self.writer.write_all(quote)?;
+        self.writer.write_all(id.as_bytes())?;
+        self.writer.write_all(quote)
+    }
+
+    fn write_escaped(&mut self, text: &str, attr_mode: bool) -> io::Result<()> {
+        let mut last = 0;
+        for (i, c) in text.char_indices() {
+            let escaped: &[u8] = match c {
+                '&' => b"&amp;",
+                '<' => b"&lt;",
+                '>' if !attr_mode => b"&gt;",
+                '"' if attr_mode => b"&quot;",
+                // XML parsers read them as spaces in attribute values.
+                '\n' if attr_mode => b"&#10;",
+                '\r' if attr_mode => b"&#13;",
+                '\t' if attr_mode => b"&#9;",
+                '\u{a0}' if !attr_mode => b"&#160;",
+                _ => continue,
+            };
+
+            self.writer.write_all(&text.as_bytes()[last..i])?;
+            self.writer.write_all(escaped)?;
+            last = i + c.len_utf8();
+        }
+
+        self.writer.write_all(&text.as_bytes()[last..])
+    }
+}
+
+fn write_qual_name<W: Write>(writer: &mut W, name: &QualName) -> io::Result<()> {
+    if let Some(ref prefix) = name.prefix {
+        if !prefix.is_empty() {
+            writer.write_all(prefix.as_bytes())?;
+            writer.write_all(b":")?;
+        }
+    }
+
+    writer.write_all(name.local.as_bytes())
+}
+
+/// Whether the attribute declares a namespace, since the serializer writes its
+/// own declarations.
+fn is_namespace_declaration(name: &QualName) -> bool {
+    name.ns == ns!(xmlns)
+        || matches!(name.prefix, Some(ref p) if &**p == "xmlns")
+        || (name.prefix.is_none() && &*name.local == "xmlns")
+}
+
+fn is_void_element(name: &LocalName) -> bool {
+    matches!(
+        &**name,
+        "area"
+            | "base"
+            | "basefont"
+            | "bgsound"
+            | "br"
+            | "col"
+            | "embed"
+            | "frame"
+            | "hr"
+            | "img"
+            | "input"
+            | "keygen"
+            | "link"
+            | "meta"
+            | "param"
+            | "source"
+            | "track"
+            | "wbr"
+    )
+}
+
+impl<W: Write> Serializer for XmlSerializer<W> {
+    fn start_elem<'a, AttrIter>(&mut self, name: QualName, attrs: AttrIter) -> io::Result<()>
+    where
+        AttrIter: Iterator<Item = AttrRef<'a>>,
+    {
+        self.close_start_tag()?;
+
+        let attrs: Vec<AttrRef> = attrs
+            .filter(|(name, _)| !is_namespace_declaration(name))
+            .collect();
+
+        let mut declarations: Vec<(Option<Prefix>, Namespace)> = vec![];
+        let element_prefix = name.prefix.clone().filter(|p| !p.is_empty());
+        if self.namespace_in_scope(&element_prefix) != name.ns {
+            declarations.push((element_prefix, name.ns.clone()));
+        }
+
+        for (attr_name, _) in attrs.iter() {
+            let prefix = match attr_name.prefix {
+                Some(ref prefix) if !prefix.is_empty() && &**prefix != "xml" => {
+                    Some(prefix.clone())
+                }
+                _ => continue,
+            };
+
+            if self.namespace_in_scope(&prefix) != attr_name.ns
+                && !declarations.iter().any(|(p, _)| *p == prefix)
+            {
+                declarations.push((prefix, attr_name.ns.clone()));
+            }
+        }
+
+        self.writer.write_all(b"<")?;
+        write_qual_name(&mut self.writer, &name)?;
+
+        for (prefix, ns) in declarations.iter() {
+            match prefix {
+                Some(prefix) => write!(self.writer, " xmlns:{}=\"", prefix)?,
+                None => self.writer.write_all(b" xmlns=\"")?,
+            }
+            self.write_escaped(ns, true)?;
+            self.writer.write_all(b"\"")?;
+        }
+
+        for (attr_name, value) in attrs {
+            self.writer.write_all(b" ")?;
+            write_qual_name(&mut self.writer, attr_name)?;
+            self.writer.write_all(b"=\"")?;
+            self.write_escaped(value, true)?;
+            self.writer.write_all(b"\"")?;
+        }
+
+        self.scopes.push(declarations);
+        self.raw_text.push(match name.local {
+            _ if name.ns != ns!(html) => None,
+            local_name!("script") => Some(("//<![CDATA[\n", "\n//]]>")),
+            local_name!("style") => Some(("/*<![CDATA[*/\n", "\n/*]]>*/")),
+            _ => None,
+        });
+        self.start_tag_open = true;
+        Ok(())
+    }
+
+    fn end_elem(&mut self, name: QualName) -> io::Result<()> {
+        self.scopes.pop();
+        self.raw_text.pop();
+
+        if self.start_tag_open {
+            self.start_tag_open = false;
+
+            if !self.xhtml {
+                return self.writer.write_all(b"/>");
+            }
+
+            if name.ns == ns!(html) && is_void_element(&name.local) {
+                return self.writer.write_all(b" />");
+            }
+
+            self.writer.write_all(b">")?;
+        }
+
+        self.writer.write_all(b"</")?;
+        write_qual_name(&mut self.writer, &name)?;
+        self.writer.write_all(b">")
+    }
+
+    fn write_text(&mut self, text: &str) -> io::Result<()> {
+        self.close_start_tag()?;
+
+        let (open, close) = match self.raw_text.last() {
+            Some(&Some(markers)) if self.xhtml => markers,
+            _ => return self.write_escaped(text, false),
+        };
+
+        if !text.contains(['<', '&']) && !text.contains("]]>") {
+            return self.writer.write_all(text.as_bytes());
+        }
+
+        self.writer.write_all(open.as_bytes())?;
+        // A CDATA section cannot hold its own end.
+        self.writer
+            .write_all(text.replace("]]>", "]]]]><![CDATA[>").as_bytes())?;
+        self.writer.write_all(close.as_bytes())
+    }
+
+    fn write_comment(&mut self, text: &str) -> io::Result<()> {
+        self.close_start_tag()?;
+
+        // "--" is not allowed inside XML comments, nor a trailing "-".
+        let mut text = text.to_string();
+        while text.contains("--") {
+            text = text.replace("--", "- -");
+        }
+        if text.ends_with('-') {
+            text.push(' ');
+        }
+
+        self.writer.write_all(b"<!--")?;
+        self.writer.write_all(text.as_bytes())?;
+        self.writer.write_all(b"-->")
+    }
+
+    fn write_doctype(&mut self, name: &str) -> io::Result<()> {
+        self.write_doctype_with_ids(name, "", "")
+    }
+
+    fn write_processing_instruction(&mut self, target: &str, data: &str) -> io::Result<()> {
+        self.close_start_tag()?;
+        self.writer.write_all(b"<?")?;
+        self.writer.write_all(target.as_bytes())?;
+        if !data.is_empty() {
+            self.writer.write_all(b" ")?;
+            self.writer.write_all(data.as_bytes())?;
+        }
+        self.writer.write_all(b"?>")
+    }
+}
+
+/// A serialized node, which unlike the events a `Serializer` receives can be
+/// looked ahead into.
+enum Item {
+    Element {
+        name: QualName,
+        attrs: Attrs,
+        children: Vec<Item>,
+    },
+    Text(String),
+    Comment(String),
+    Doctype(String),
+    ProcessingInstruction(String, String),
+}
+
+type Attrs = Vec<(QualName, String)>;
+
+/// Collects the items serialized by `SerializableNodeRef`.
+#[derive(Default)]
+struct ItemBuilder {
+    items: Vec<Item>,
+    open: Vec<(QualName, Attrs, Vec<Item>)>,
+}
+
+impl ItemBuilder {
+    fn push(&mut self, item: Item) {
+        match self.open.last_mut() {
+            Some((_, _, children)) => children.push(item),
+            None => self.items.push(item),
+        }
+    }
+}
+
+impl Serializer for ItemBuilder {
+    fn start_elem<'a, AttrIter>(&mut self, name: QualName, attrs: AttrIter) -> io::Result<()>
+    where
+        AttrIter: Iterator<Item = AttrRef<'a>>,
+    {
+        let attrs = attrs
+            .map(|(name, value)| (name.clone(), value.to_string()))
+            .collect();
+        self.open.push((name, attrs, vec![]));
+        Ok(())
+    }
+
+    fn end_elem(&mut self, _name: QualName) -> io::Result<()> {
+        if let Some((name, attrs, children)) = self.open.pop() {
+            self.push(Item::Element {
+                name,
+                attrs,
+                children,
+            });
+        }
+        Ok(())
+    }
+
+    fn write_text(&mut self, text: &str) -> io::Result<()> {
+        self.push(Item::Text(text.to_string()));
+        Ok(())
+    }
+
+    fn write_comment(&mut self, text: &str) -> io::Result<()> {
+        self.push(Item::Comment(text.to_string()));
+        Ok(())
+    }
+
+    fn write_doctype(&mut self, name: &str) -> io::Result<()> {
+        self.push(Item::Doctype(name.to_string()));
+        Ok(())
+    }
+
+    fn write_processing_instruction(&mut self, target: &str, data: &str) -> io::Result<()> {
+        self.push(Item::ProcessingInstruction(
+            target.to_string(),
+            data.to_string(),
+        ));
+        Ok(())
+    }
+}
+
+/// Elements laid out within a line of text, which pretty printing keeps on
+/// the line and minifying keeps the whitespace around.
+fn is_inline_element(name: &QualName) -> bool {
+    if name.ns != ns!(html) {
+        return true;
+    }
+
+    matches!(
+        &*name.local,
+        "a" | "abbr"
+            | "acronym"
+            | "audio"
+            | "b"
+            | "bdi"
+            | "bdo"
+            | "big"
+            | "br"
+            | "button"
+            | "canvas"
+            | "cite"
+            | "code"
+            | "data"
+            | "del"
+            | "dfn"
+            | "em"
+            | "embed"
+            | "font"
+            | "i"
+            | "iframe"
+            | "img"
+            | "input"
+            | "ins"
+            | "kbd"
+            | "label"
+            | "map"
+            | "mark"
+            | "meter"
+            | "noscript"
+            | "object"
+            | "output"
+            | "picture"
+            | "progress"
+            | "q"
+            | "ruby"
+            | "rp"
+            | "rt"
+            | "s"
+            | "samp"
+            | "select"
+            | "small"
+            | "span"
+            | "strike"
+            | "strong"
+            | "sub"
+            | "sup"
+            | "textarea"
+            | "time"
+            | "tt"
+            | "u"
+            | "var"
+            | "video"
+            | "wbr"
+    )
+}
+
+/// Elements whose contents are serialized as they are.
+fn is_preformatted(name: &QualName) -> bool {
+    is_raw_text(name)
+        || (name.ns == ns!(html) && matches!(&*name.local, "listing" | "pre" | "textarea"))
+}
+
+/// Elements whose text is not escaped, as the HTML serializer does with
+/// scripting enabled.
+fn is_raw_text(name: &QualName) -> bool {
+    name.ns == ns!(html)
+        && matches!(
+            &*name.local,
+            "iframe"
+                | "noembed"
+                | "noframes"
+                | "noscript"
+                | "plaintext"
+                | "script"
+                | "style"
+                | "xmp"
+        )
+}
+
+fn is_block(item: &Item) -> bool {
+    match item {
+        Item::Element { name, .. } => !is_inline_element(name),
+        Item::Doctype(_) | Item::ProcessingInstruction(..) => true,
+        Item::Text(_) | Item::Comment(_) => false,
+    }
+}
+
+fn start_tag(out: &mut String, name: &QualName, attrs: &[(QualName, String)], minify: bool) {
+    out.push('<');
+    out.push_str(&name.local);
+
+    for (name, value) in attrs {
+        out.push(' ');
+        match name.ns {
+            ns!(xml) => out.push_str("xml:"),
+            ns!(xmlns) if &*name.local != "xmlns" => out.push_str("xmlns:"),
+            ns!(xlink) => out.push_str("xlink:"),
+            _ => (),
+        }
+        out.push_str(&name.local);
+
+        if minify && value.is_empty() {
+            continue;
+        }
+
+        if minify && is_unquotable(value) {
+            out.push('=');
+            escape_html(out, value, true);
+        } else {
+            out.push_str("=\"");
+            escape_html(out, value, true);
+            out.push('"');
+        }
+    }
+
+    out.push('>');
+}
+
+fn end_tag(out: &mut String, name: &QualName) {
+    if name.ns == ns!(html) && is_void_element(&name.local) {
+        return;
+    }
+
+    out.push_str("</");
+    out.push_str(&name.local);
+    out.push('>');
+}
+
+/// Writes an item without changing its whitespace.
+fn verbatim(out: &mut String, item: &Item, raw_text: bool, minify: bool) {
+    match item {
+        Item::Element {
+            name,
+            attrs,
+            children,
+        } => {
+            start_tag(out, name, attrs, minify);
+            for child in children {
+                verbatim(out, child, is_raw_text(name), minify);
+            }
+            end_tag(out, name);
+        }
+        Item::Text(text) if raw_text => out.push_str(text),
+        Item::Text(text) => escape_html(out, text, false),
+        Item::Comment(text) => {
+            out.push_str("<!--");
+            out.push_str(text);
+            out.push_str("-->");
+        }
+        Item::Doctype(name) => {
+            out.push_str("<!DOCTYPE ");
+            out.push_str(name);
+            out.push('>');
+        }
+        Item::ProcessingInstruction(target, data) => {
+            out.push_str("<?");
+            out.push_str(target);
+            out.push(' ');
+            out.push_str(data);
+            out.push('>');
+        }
+    }
+}
+
+fn escape_html(out: &mut String, text: &str, attr_mode: bool) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '\u{a0}' => out.push_str("&nbsp;"),
+            '"' if attr_mode => out.push_str("&quot;"),
+            '<' if !attr_mode => out.push_str("&lt;"),
+            '>' if !attr_mode => out.push_str("&gt;"),
+            c => out.push(c),
+        }
+    }
+}
+
+/// Whether an attribute value can be written without quotes.
+fn is_unquotable(value: &str) -> bool {
+    !value.is_empty()
+        && !value.chars().any(|c| {
+            matches!(
+                c,
+                '\t' | '\n' | '\x0C' | '\r' | ' ' | '"' | '\'' | '=' | '<' | '>' | '`'
+            )
+        })
+}
+
+/// A line of inline content, with whitespace collapsed.
+#[derive(Default)]
+struct Line {
+    text: String,
+    pending_space: bool,
+}
+
+impl Line {
+    fn push_str(&mut self, s: &str) {
+        if self.pending_space {
+            self.text.push(' ');
+            self.pending_space = false;
+        }
+        self.text.push_str(s);
+    }
+
+    fn push_text(&mut self, text: &str) {
+        for c in text.chars() {
+            if c.is_ascii_whitespace() {
+                self.pending_space |= !self.text.is_empty();
+                continue;
+            }
+
+            if self.pending_space {
+                self.text.push(' ');
+                self.pending_space = false;
+            }
+            escape_html(&mut self.text, c.encode_utf8(&mut [0; 4]), false);
+        }
+    }
+}
+
+struct Formatter<'a> {
+    options: &'a SerializeOptions,
+    out: String,
+}
+
+impl<'a> Formatter<'a> {
+    fn line(&mut self, depth: usize, text: &str) {
+        if !self.out.is_empty() {
+            self.out.push('\n');
+        }
+        for _ in 0..depth {
+            self.out.push_str(&self.options.indent);
+        }
+        self.out.push_str(text);
+    }
+
+    /// Puts each block item on its own line, and each run of inline items in
+    /// between on a line of its own.
+    fn pretty_block(&mut self, items: &[Item], depth: usize) {
+        let mut run = Line::default();
+
+        for item in items {
+            if !is_block(item) {
+                pretty_inline(&mut run, item);
+                continue;
+            }
+
+            if !run.text.is_empty() {
+                self.line(depth, &run.text);
+            }
+            run = Line::default();
+
+            let (name, attrs, children) = match item {
+                Item::Element {
+                    name,
+                    attrs,
+                    children,
+                } if !is_preformatted(name) => (name, attrs, children),
+                _ => {
+                    let mut text = String::new();
+                    verbatim(&mut text, item, false, false);
+                    self.line(depth, &text);
+                    continue;
+                }
+            };
+
+            let mut start = String::new();
+            start_tag(&mut start, name, attrs, false);
+            let mut end = String::new();
+            end_tag(&mut end, name);
+
+            if children.iter().any(is_block) {
+                self.line(depth, &start);
+                self.pretty_block(children, depth + 1);
+                self.line(depth, &end);
+            } else {
+                let mut content = Line::default();
+                for child in children {
+                    pretty_inline(&mut content, child);
+                }
+                self.line(depth, &(start + &content.text + &end));
+            }
+        }
+
+        if !run.text.is_empty() {
+            self.line(depth, &run.text);
+        }
+    }
+
+    fn minify_children(&mut self, items: &[Item], parent: Option<&QualName>) {
+        let items: Vec<&Item> = items
+            .iter()
+            .filter(|item| !matches!(item, Item::Comment(_)))
+            .collect();
+        let in_block = !matches!(parent, Some(name) if is_inline_element(name));
+
+        for (i, item) in items.iter().enumerate() {
+            match item {
+                Item::Element {
+                    name,
+                    attrs,
+                    children,
+                } => {
+                    start_tag(&mut self.out, name, attrs, true);
+                    if is_preformatted(name) {
+                        for child in children {
+                            verbatim(&mut self.out, child, is_raw_text(name), true);
+                        }
+                    } else {
+                        self.minify_children(children, Some(name));
+                    }
+                    end_tag(&mut self.out, name);
+                }
+                Item::Text(text) => {
+                    // Whitespace next to a block boundary is not rendered.
+                    let at_start = match i {
+                        0 => in_block,
+                        _ => is_block(items[i - 1]),
+                    };
+                    let at_end = match items.get(i + 1) {
+                        Some(next) => is_block(next),
+                        None => in_block,
+                    };
+
+                    let mut line = Line {
+                        text: String::new(),
+                        pending_space: !at_start
+                            && text.starts_with(|c: char| c.is_ascii_whitespace()),
+                    };
+                    line.push_text(text);
+                    if line.pending_space && !at_end {
+                        line.text.push(' ');
+                    }
+                    self.out.push_str(&line.text);
+                }
+                _ => verbatim(&mut self.out, item, false, true),
+            }
+        }
+    }
+}
+
+fn pretty_inline(line: &mut Line, item: &Item) {
+    match item {
+        Item::Element {
+            name,
+            attrs,
+            children,
+        } if !is_preformatted(name) => {
+            let mut tag = String::new();
+            start_tag(&mut tag, name, attrs, false);
+            line.push_str(&tag);
+
+            for child in children {
+                pretty_inline(line, child);
+            }
+
+            let mut tag = String::new();
+            end_tag(&mut tag, name);
+            if !tag.is_empty() {
+                line.push_str(&tag);
+            }
+        }
+        Item::Text(text) => line.push_text(text),
+        _ => {
+            let mut text = String::new();
+            verbatim(&mut text, item, false, false);
+            line.push_str(&text);
+        }
+    }
+}
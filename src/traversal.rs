@@ -1,23 +1,38 @@
-use crate::matcher::{MatchScope, Matcher, Matches};
+use crate::matcher::{MatchScope, Matcher, Matches, SelectorError};
+use crate::positional::PositionalSelector;
 use crate::Document;
 use crate::Node;
 use crate::Selection;
 use std::collections::HashSet;
+use std::iter;
 use std::vec::IntoIter;
 
 impl Document {
     /// Gets the descendants of the root document node in the current, filter by a selector.
     /// It returns a new selection object containing these matched elements.
     ///
+    /// Besides CSS, the selector may use jQuery's positional pseudo-classes
+    /// `:eq(n)`, `:gt(n)`, `:lt(n)`, `:first`, `:last`, `:even` and `:odd`,
+    /// which filter the elements matched so far by their index.
+    ///
     /// # Panics
     ///
     /// Panics if failed to parse the given CSS selector.
     pub fn select(&self, sel: &str) -> Selection {
-        let matcher = Matcher::new(sel).expect("Invalid CSS selector");
-        let root = self.tree.root();
-        Selection {
-            nodes: Matches::from_one(root, matcher.clone(), MatchScope::IncludeNode).collect(),
+        self.select_checked(sel).expect("Invalid CSS selector")
+    }
+
+    /// Works like `select` but returns an error describing what is wrong with
+    /// the selector instead of panicking.
+    pub fn select_checked(&self, sel: &str) -> Result<Selection, SelectorError> {
+        let namespaces = self.tree.namespaces();
+        if let Some(selector) = PositionalSelector::parse(sel, namespaces, false)? {
+            let nodes = selector.select(&[self.tree.root()], MatchScope::IncludeNode);
+            return Ok(Selection { nodes });
         }
+
+        let matcher = Matcher::with_namespaces(sel, namespaces)?;
+        Ok(self.select_matcher(&matcher))
     }
 
     /// Alias for `select`, it gets the descendants of the root document node in the current, filter by a selector.
@@ -33,7 +48,7 @@ impl Document {
     /// Gets the descendants of the root document node in the current, filter by a selector.
     /// It returns a new selection object containing these matched elements.
     pub fn try_select(&self, sel: &str) -> Option<Selection> {
-        match Matcher::new(sel) {
+        match Matcher::with_namespaces(sel, self.tree.namespaces()) {
             Ok(matcher) => {
                 let root = self.tree.root();
                 let nodes: Vec<Node> =
@@ -63,19 +78,35 @@ impl<'a> Selection<'a> {
     /// elements, filter by a selector. It returns a new Selection object
     /// containing these matched elements.
     ///
+    /// Like with `Element.querySelectorAll`, the whole selector must match
+    /// but only its rightmost part needs to be inside an element, so `div p`
+    /// finds the paragraphs inside an element which is itself inside a div.
+    /// `:scope` stands for each element, as in `:scope div p`, and a selector
+    /// starting with a combinator is relative to each element: `> li` finds
+    /// the children, `+ p` and `~ h2` the following siblings.
+    ///
     /// # Panics
     ///
     /// Panics if failed to parse the given CSS selector.
     pub fn select(&self, sel: &str) -> Selection<'a> {
-        let matcher = Matcher::new(sel).expect("Invalid CSS seletor");
-        Selection {
-            nodes: Matches::from_list(
-                self.nodes.clone().into_iter(),
-                matcher,
-                MatchScope::ChildrenOnly,
-            )
-            .collect(),
+        self.select_checked(sel).expect("Invalid CSS seletor")
+    }
+
+    /// Works like `select` but returns an error describing what is wrong with
+    /// the selector instead of panicking.
+    pub fn select_checked(&self, sel: &str) -> Result<Selection<'a>, SelectorError> {
+        if let Some(selector) = self.positional_selector(sel)? {
+            let nodes = selector.select(&self.nodes, MatchScope::ChildrenOnly);
+            return Ok(Selection::in_document_order(nodes));
         }
+
+        if let Some(selector) = self.scoped_selector(sel)? {
+            let nodes = selector.select(&self.nodes);
+            return Ok(Selection::in_document_order(nodes));
+        }
+
+        let matcher = self.matcher(sel)?;
+        Ok(self.select_matcher(&matcher))
     }
 
     /// Alias for `select`, it gets the descendants of each element in the current set of matched
@@ -93,7 +124,7 @@ impl<'a> Selection<'a> {
     /// elements, filter by a selector. It returns a new Selection object
     /// containing these matched elements.
     pub fn try_select(&self, sel: &str) -> Option<Selection<'a>> {
-        match Matcher::new(sel) {
+        match self.matcher(sel) {
             Ok(matcher) => {
                 let nodes: Vec<Node> = Matches::from_list(
                     self.nodes.clone().into_iter(),
@@ -102,7 +133,7 @@ impl<'a> Selection<'a> {
                 )
                 .collect();
                 if nodes.len() > 0 {
-                    Some(Selection { nodes })
+                    Some(Selection::in_document_order(nodes))
                 } else {
                     None
                 }
@@ -115,14 +146,14 @@ impl<'a> Selection<'a> {
     /// elements, filter by a matcher. It returns a new Selection object
     /// containing these matched elements.
     pub fn select_matcher(&self, matcher: &Matcher) -> Selection<'a> {
-        Selection {
-            nodes: Matches::from_list(
+        Selection::in_document_order(
+            Matches::from_list(
                 self.nodes.clone().into_iter(),
                 matcher.clone(),
                 MatchScope::ChildrenOnly,
             )
             .collect(),
-        }
+        )
     }
 
     /// Returns a slice of underlying nodes.
@@ -150,7 +181,126 @@ impl<'a> Selection<'a> {
             }
         }
 
-        Self { nodes: result }
+        Selection::in_document_order(result)
+    }
+
+    /// For each element in the selection, gets the first element that
+    /// matches the selector by testing the element itself and then its
+    /// ancestors. It returns a new Selection object containing these
+    /// elements, each listed once. An invalid selector matches nothing.
+    pub fn closest(&self, sel: &str) -> Selection<'a> {
+        match self.matcher(sel) {
+            Ok(matcher) => self.closest_matcher(&matcher),
+            Err(_) => Selection::default(),
+        }
+    }
+
+    /// Works like `closest` but filters by a matcher.
+    pub fn closest_matcher(&self, matcher: &Matcher) -> Selection<'a> {
+        self.closest_by(|node| matcher.match_element(node))
+    }
+
+    /// Works like `closest` but keeps the elements which are in `sel`.
+    pub fn closest_selection(&self, sel: &Selection) -> Selection<'a> {
+        let ids = sel.ids();
+        self.closest_by(|node| ids.contains(&node.id))
+    }
+
+    /// Gets the ancestor elements of each element in the selection. It
+    /// returns a new Selection object containing these elements, each listed
+    /// once.
+    pub fn parents(&self) -> Selection<'a> {
+        self.parents_by(|_| true, |_| false)
+    }
+
+    /// Works like `parents` but only keeps the ancestors matching the
+    /// selector. An invalid selector matches nothing.
+    pub fn parents_filtered(&self, sel: &str) -> Selection<'a> {
+        match self.matcher(sel) {
+            Ok(matcher) => self.parents_filtered_matcher(&matcher),
+            Err(_) => Selection::default(),
+        }
+    }
+
+    /// Works like `parents_filtered` but filters by a matcher.
+    pub fn parents_filtered_matcher(&self, matcher: &Matcher) -> Selection<'a> {
+        self.parents_by(|node| matcher.match_element(node), |_| false)
+    }
+
+    /// Works like `parents` but only keeps the ancestors which are in `sel`.
+    pub fn parents_filtered_selection(&self, sel: &Selection) -> Selection<'a> {
+        let ids = sel.ids();
+        self.parents_by(|node| ids.contains(&node.id), |_| false)
+    }
+
+    /// Gets the ancestor elements of each element in the selection, up to
+    /// but not including the first one matching the selector. An invalid
+    /// selector matches nothing, so all the ancestors are kept.
+    pub fn parents_until(&self, sel: &str) -> Selection<'a> {
+        match self.matcher(sel) {
+            Ok(matcher) => self.parents_until_matcher(&matcher),
+            Err(_) => self.parents(),
+        }
+    }
+
+    /// Works like `parents_until` but stops at an element matching a matcher.
+    pub fn parents_until_matcher(&self, matcher: &Matcher) -> Selection<'a> {
+        self.parents_by(|_| true, |node| matcher.match_element(node))
+    }
+
+    /// Works like `parents_until` but stops at an element which is in `sel`.
+    pub fn parents_until_selection(&self, sel: &Selection) -> Selection<'a> {
+        let ids = sel.ids();
+        self.parents_by(|_| true, |node| ids.contains(&node.id))
+    }
+
+    fn closest_by<F>(&self, f: F) -> Selection<'a>
+    where
+        F: Fn(&Node<'a>) -> bool,
+    {
+        let mut result = Vec::with_capacity(self.length());
+        let mut set = HashSet::with_capacity(self.length());
+
+        for node in self.nodes() {
+            let mut current = Some(node.clone());
+            while let Some(node) = current {
+                if node.is_element() && f(&node) {
+                    if set.insert(node.id) {
+                        result.push(node);
+                    }
+                    break;
+                }
+                current = node.parent();
+            }
+        }
+
+        Selection::in_document_order(result)
+    }
+
+    /// Walks up the ancestor elements of each element in the selection,
+    /// keeping those passing `filter` until one passes `until`.
+    fn parents_by<F, U>(&self, filter: F, until: U) -> Selection<'a>
+    where
+        F: Fn(&Node<'a>) -> bool,
+        U: Fn(&Node<'a>) -> bool,
+    {
+        let mut result = Vec::with_capacity(self.length());
+        let mut set = HashSet::with_capacity(self.length());
+
+        for node in self.nodes() {
+            let mut parent = node.parent();
+            while let Some(node) = parent {
+                if !node.is_element() || until(&node) {
+                    break;
+                }
+                parent = node.parent();
+                if filter(&node) && set.insert(node.id) {
+                    result.push(node);
+                }
+            }
+        }
+
+        Selection::in_document_order(result)
     }
 
     /// Gets the child elements of each element in the selection.
@@ -168,7 +318,7 @@ impl<'a> Selection<'a> {
             }
         }
 
-        Self { nodes: result }
+        Selection::in_document_order(result)
     }
 
     #[deprecated(since = "0.1.6", note = "Please use `next_sibling`")]
@@ -193,7 +343,7 @@ impl<'a> Selection<'a> {
             }
         }
 
-        Self { nodes: result }
+        Selection::in_document_order(result)
     }
 
     /// Gets the immediately previous sibling of each element in the
@@ -211,7 +361,182 @@ impl<'a> Selection<'a> {
             }
         }
 
-        Self { nodes: result }
+        Selection::in_document_order(result)
+    }
+
+    /// Gets all the following siblings of each element in the selection. It
+    /// returns a new Selection object containing these elements, each listed
+    /// once.
+    pub fn next_all(&self) -> Selection<'a> {
+        self.siblings_by(Siblings::Next, |_| true, |_| false)
+    }
+
+    /// Works like `next_all` but only keeps the siblings matching the
+    /// selector. An invalid selector matches nothing.
+    pub fn next_all_filtered(&self, sel: &str) -> Selection<'a> {
+        match self.matcher(sel) {
+            Ok(matcher) => self.next_all_filtered_matcher(&matcher),
+            Err(_) => Selection::default(),
+        }
+    }
+
+    /// Works like `next_all_filtered` but filters by a matcher.
+    pub fn next_all_filtered_matcher(&self, matcher: &Matcher) -> Selection<'a> {
+        self.siblings_by(
+            Siblings::Next,
+            |node| matcher.match_element(node),
+            |_| false,
+        )
+    }
+
+    /// Works like `next_all` but only keeps the siblings which are in `sel`.
+    pub fn next_all_filtered_selection(&self, sel: &Selection) -> Selection<'a> {
+        let ids = sel.ids();
+        self.siblings_by(Siblings::Next, |node| ids.contains(&node.id), |_| false)
+    }
+
+    /// Gets all the previous siblings of each element in the selection. It
+    /// returns a new Selection object containing these elements, each listed
+    /// once.
+    pub fn prev_all(&self) -> Selection<'a> {
+        self.siblings_by(Siblings::Prev, |_| true, |_| false)
+    }
+
+    /// Works like `prev_all` but only keeps the siblings matching the
+    /// selector. An invalid selector matches nothing.
+    pub fn prev_all_filtered(&self, sel: &str) -> Selection<'a> {
+        match self.matcher(sel) {
+            Ok(matcher) => self.prev_all_filtered_matcher(&matcher),
+            Err(_) => Selection::default(),
+        }
+    }
+
+    /// Works like `prev_all_filtered` but filters by a matcher.
+    pub fn prev_all_filtered_matcher(&self, matcher: &Matcher) -> Selection<'a> {
+        self.siblings_by(
+            Siblings::Prev,
+            |node| matcher.match_element(node),
+            |_| false,
+        )
+    }
+
+    /// Works like `prev_all` but only keeps the siblings which are in `sel`.
+    pub fn prev_all_filtered_selection(&self, sel: &Selection) -> Selection<'a> {
+        let ids = sel.ids();
+        self.siblings_by(Siblings::Prev, |node| ids.contains(&node.id), |_| false)
+    }
+
+    /// Gets the sibling elements of each element in the selection. It
+    /// returns a new Selection object containing these elements, each listed
+    /// once.
+    pub fn siblings(&self) -> Selection<'a> {
+        self.siblings_by(Siblings::All, |_| true, |_| false)
+    }
+
+    /// Works like `siblings` but only keeps the siblings matching the
+    /// selector. An invalid selector matches nothing.
+    pub fn siblings_filtered(&self, sel: &str) -> Selection<'a> {
+        match self.matcher(sel) {
+            Ok(matcher) => self.siblings_filtered_matcher(&matcher),
+            Err(_) => Selection::default(),
+        }
+    }
+
+    /// Works like `siblings_filtered` but filters by a matcher.
+    pub fn siblings_filtered_matcher(&self, matcher: &Matcher) -> Selection<'a> {
+        self.siblings_by(Siblings::All, |node| matcher.match_element(node), |_| false)
+    }
+
+    /// Works like `siblings` but only keeps the siblings which are in `sel`.
+    pub fn siblings_filtered_selection(&self, sel: &Selection) -> Selection<'a> {
+        let ids = sel.ids();
+        self.siblings_by(Siblings::All, |node| ids.contains(&node.id), |_| false)
+    }
+
+    /// Gets the following siblings of each element in the selection, up to
+    /// but not including the first one matching the selector. An invalid
+    /// selector matches nothing, so all the following siblings are kept.
+    pub fn next_until(&self, sel: &str) -> Selection<'a> {
+        match self.matcher(sel) {
+            Ok(matcher) => self.next_until_matcher(&matcher),
+            Err(_) => self.next_all(),
+        }
+    }
+
+    /// Works like `next_until` but stops at a sibling matching a matcher.
+    pub fn next_until_matcher(&self, matcher: &Matcher) -> Selection<'a> {
+        self.siblings_by(Siblings::Next, |_| true, |node| matcher.match_element(node))
+    }
+
+    /// Works like `next_until` but stops at a sibling which is in `sel`.
+    pub fn next_until_selection(&self, sel: &Selection) -> Selection<'a> {
+        let ids = sel.ids();
+        self.siblings_by(Siblings::Next, |_| true, |node| ids.contains(&node.id))
+    }
+
+    /// Gets the previous siblings of each element in the selection, from the
+    /// nearest one back to but not including the first one matching the
+    /// selector. An invalid selector matches nothing, so all the previous
+    /// siblings are kept.
+    pub fn prev_until(&self, sel: &str) -> Selection<'a> {
+        match self.matcher(sel) {
+            Ok(matcher) => self.prev_until_matcher(&matcher),
+            Err(_) => self.prev_all(),
+        }
+    }
+
+    /// Works like `prev_until` but stops at a sibling matching a matcher.
+    pub fn prev_until_matcher(&self, matcher: &Matcher) -> Selection<'a> {
+        self.siblings_by(Siblings::Prev, |_| true, |node| matcher.match_element(node))
+    }
+
+    /// Works like `prev_until` but stops at a sibling which is in `sel`.
+    pub fn prev_until_selection(&self, sel: &Selection) -> Selection<'a> {
+        let ids = sel.ids();
+        self.siblings_by(Siblings::Prev, |_| true, |node| ids.contains(&node.id))
+    }
+
+    /// Walks the sibling elements of each element in the selection, keeping
+    /// those passing `filter` until one passes `until`.
+    fn siblings_by<F, U>(&self, siblings: Siblings, filter: F, until: U) -> Selection<'a>
+    where
+        F: Fn(&Node<'a>) -> bool,
+        U: Fn(&Node<'a>) -> bool,
+    {
+        let mut result = Vec::with_capacity(self.length());
+        let mut set = HashSet::with_capacity(self.length());
+
+        for node in self.nodes() {
+            let candidates: Vec<Node<'a>> = match siblings {
+                Siblings::Next => {
+                    iter::successors(node.next_element_sibling(), Node::next_element_sibling)
+                        .collect()
+                }
+                Siblings::Prev => {
+                    iter::successors(node.prev_element_sibling(), Node::prev_element_sibling)
+                        .collect()
+                }
+                Siblings::All => match node.parent() {
+                    Some(parent) => parent
+                        .children()
+                        .into_iter()
+                        .filter(|child| child.is_element() && child.id != node.id)
+                        .collect(),
+                    None => vec![],
+                },
+            };
+
+            for sibling in candidates {
+                if until(&sibling) {
+                    break;
+                }
+                if filter(&sibling) && set.insert(sibling.id) {
+                    result.push(sibling);
+                }
+            }
+        }
+
+        Selection::in_document_order(result)
     }
 
     /// Reduces the set of matched elements to the first in the set.
@@ -242,6 +567,14 @@ impl<'a> Selection<'a> {
     }
 }
 
+/// Which siblings of an element `Selection::siblings_by` walks.
+#[derive(Clone, Copy)]
+enum Siblings {
+    Next,
+    Prev,
+    All,
+}
+
 /// Iterator over a collection of matched elements.
 pub struct Selections<I> {
     iter: IntoIter<I>,
@@ -0,0 +1,63 @@
+#![cfg(feature = "async")]
+
+use futures::executor::block_on;
+use futures::io::{AsyncRead, Cursor};
+use nipper::Document;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// Yields `Pending` before every read and returns at most `chunk` bytes at a time.
+struct SlowReader {
+    data: Vec<u8>,
+    pos: usize,
+    chunk: usize,
+    ready: bool,
+}
+
+impl AsyncRead for SlowReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        if !self.ready {
+            self.ready = true;
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
+        }
+
+        self.ready = false;
+        let n = buf.len().min(self.chunk).min(self.data.len() - self.pos);
+        buf[..n].copy_from_slice(&self.data[self.pos..self.pos + n]);
+        self.pos += n;
+        Poll::Ready(Ok(n))
+    }
+}
+
+#[test]
+fn test_from_async_reader() {
+    let reader = Cursor::new(b"<div><a href=\"/1\">One</a></div>".to_vec());
+    let doc = block_on(Document::from_async_reader(reader)).unwrap();
+    assert_eq!(doc.select("a").attr("href").unwrap().to_string(), "/1");
+}
+
+#[test]
+fn test_from_async_reader_chunked() {
+    let html = format!(
+        "<meta charset=\"gbk\"><ul>{}</ul>",
+        "<li>你好</li>".repeat(1000)
+    );
+    let (bytes, _, _) = encoding_rs::GBK.encode(&html);
+    let reader = SlowReader {
+        data: bytes.into_owned(),
+        pos: 0,
+        chunk: 7,
+        ready: false,
+    };
+
+    let doc = block_on(Document::from_async_reader(reader)).unwrap();
+    assert_eq!(doc.encoding, encoding_rs::GBK);
+    assert_eq!(doc.select("li").length(), 1000);
+    assert_eq!(doc.select("li").last().text().to_string(), "你好");
+}
@@ -0,0 +1,337 @@
+use nipper::{Document, ParseOptions};
+
+#[test]
+fn test_fragment_keeps_table_cells() {
+    let doc = Document::fragment("<td>a</td><td>b</td>", "tr");
+    assert_eq!(doc.select("td").length(), 2);
+    assert_eq!(doc.select("html, head, body").length(), 0);
+    assert_eq!(doc.select("td").text().to_string(), "ab");
+}
+
+#[test]
+fn test_fragment_document_drops_table_cells() {
+    let doc = Document::from("<td>a</td><td>b</td>");
+    assert_eq!(doc.select("td").length(), 0);
+    assert_eq!(doc.select("body").length(), 1);
+}
+
+#[test]
+fn test_fragment_html() {
+    let doc = Document::fragment(
+        r#"<p class="intro">Hello <b>world</b></p><p>Bye</p>"#,
+        "body",
+    );
+    assert_eq!(
+        doc.html().to_string(),
+        r#"<p class="intro">Hello <b>world</b></p><p>Bye</p>"#
+    );
+    assert_eq!(doc.select("p.intro b").text().to_string(), "world");
+    assert_eq!(doc.select("p").length(), 2);
+}
+
+#[test]
+fn test_from_bytes_meta_charset() {
+    let html =
+        r#"<html><head><meta charset="Shift_JIS"></head><body><p>こんにちは</p></body></html>"#;
+    let (bytes, _, _) = encoding_rs::SHIFT_JIS.encode(html);
+    let doc = Document::from_bytes(&bytes);
+    assert_eq!(doc.encoding, encoding_rs::SHIFT_JIS);
+    assert_eq!(doc.select("p").text().to_string(), "こんにちは");
+}
+
+#[test]
+fn test_from_bytes_http_equiv() {
+    let html = r#"<!-- <meta charset="utf-8"> --><meta http-equiv="Content-Type" content="text/html; charset=gbk"><p>你好</p>"#;
+    let (bytes, _, _) = encoding_rs::GBK.encode(html);
+    let doc = Document::from_bytes(&bytes);
+    assert_eq!(doc.encoding, encoding_rs::GBK);
+    assert_eq!(doc.select("p").text().to_string(), "你好");
+}
+
+#[test]
+fn test_from_bytes_content_without_pragma() {
+    let doc = Document::from_bytes(b"<meta content=\"text/html; charset=gbk\"><p>hi</p>");
+    assert_eq!(doc.encoding, encoding_rs::UTF_8);
+}
+
+#[test]
+fn test_from_bytes_bom() {
+    let doc = Document::from_bytes(b"\xEF\xBB\xBF<meta charset=\"gbk\"><p>caf\xC3\xA9</p>");
+    assert_eq!(doc.encoding, encoding_rs::UTF_8);
+    assert_eq!(doc.select("p").text().to_string(), "café");
+}
+
+#[test]
+fn test_from_bytes_with_charset() {
+    let (bytes, _, _) = encoding_rs::SHIFT_JIS.encode("<p>テスト</p>");
+    let doc = Document::from_bytes_with_charset(&bytes, Some("text/html; charset=Shift_JIS"));
+    assert_eq!(doc.encoding, encoding_rs::SHIFT_JIS);
+    assert_eq!(doc.select("p").text().to_string(), "テスト");
+
+    let doc = Document::from_bytes_with_charset(&bytes, Some("shift_jis"));
+    assert_eq!(doc.encoding, encoding_rs::SHIFT_JIS);
+}
+
+#[test]
+fn test_from_bytes_fallback() {
+    let doc = Document::from_bytes("<p>café</p>".as_bytes());
+    assert_eq!(doc.encoding, encoding_rs::UTF_8);
+
+    let doc = Document::from_bytes(b"<p>caf\xE9</p>");
+    assert_eq!(doc.encoding, encoding_rs::WINDOWS_1252);
+    assert_eq!(doc.select("p").text().to_string(), "café");
+}
+
+#[test]
+fn test_from_reader() {
+    let (bytes, _, _) = encoding_rs::GBK.encode("<meta charset=gbk><p>你好</p>");
+    let doc = Document::from_reader(&bytes[..]).unwrap();
+    assert_eq!(doc.encoding, encoding_rs::GBK);
+    assert_eq!(doc.select("p").text().to_string(), "你好");
+}
+
+#[test]
+fn test_parse_options_scripting_disabled() {
+    let html =
+        r#"<body><noscript><img src="pixel.gif"><a href="/plain">plain</a></noscript></body>"#;
+
+    let doc = ParseOptions::new().parse(html);
+    assert_eq!(doc.select("noscript a").length(), 0);
+
+    let doc = ParseOptions::new().scripting_enabled(false).parse(html);
+    assert_eq!(
+        doc.select("noscript a").attr("href").unwrap().to_string(),
+        "/plain"
+    );
+    assert_eq!(doc.select("noscript img").length(), 1);
+}
+
+#[test]
+fn test_parse_options_fragment() {
+    let html = "<noscript><b>no js</b></noscript>";
+    let doc = ParseOptions::new()
+        .scripting_enabled(false)
+        .parse_fragment(html, "div");
+    assert_eq!(doc.select("noscript b").text().to_string(), "no js");
+}
+
+#[test]
+fn test_parse_options_drop_doctype() {
+    let html = "<!DOCTYPE html><p>a</p>";
+    assert!(ParseOptions::new()
+        .parse(html)
+        .html()
+        .starts_with("<!DOCTYPE html>"));
+    assert!(ParseOptions::new()
+        .drop_doctype(true)
+        .parse(html)
+        .html()
+        .starts_with("<html>"));
+}
+
+#[test]
+fn test_parse_options_exact_errors() {
+    let html = "<p>a &nosuchentity; b</p></div>";
+    let loose = ParseOptions::new().parse(html);
+    let exact = ParseOptions::new().exact_errors(true).parse(html);
+    assert!(exact.errors.len() >= loose.errors.len());
+    assert!(!exact.errors.is_empty());
+}
+
+#[test]
+fn test_source_positions() {
+    let html = "<!DOCTYPE html>\n<html>\n<body>\n  <div id=\"a\">\n    <p>one</p><p>two</p>\n  </div>\n</body>\n</html>";
+    let doc = ParseOptions::new().track_positions(true).parse(html);
+
+    let div = doc.select("#a");
+    let pos = div.get(0).unwrap().source_position().unwrap();
+    assert_eq!((pos.line, pos.column), (4, 3));
+    assert_eq!(&html[pos.offset..pos.offset + 4], "<div");
+
+    let ps = doc.select("p");
+    let first = ps.get(0).unwrap().source_position().unwrap();
+    let second = ps.get(1).unwrap().source_position().unwrap();
+    assert_eq!((first.line, first.column), (5, 5));
+    assert_eq!((second.line, second.column), (5, 15));
+
+    // The <head> is implied and never appears in the source.
+    assert_eq!(doc.select("head").get(0).unwrap().source_position(), None);
+}
+
+#[test]
+fn test_source_positions_skip_comments_and_raw_text() {
+    let html = "<body>\n<!-- <p>old</p> -->\n<script>if (a <p) {}</script>\n<p>new</p>";
+    let doc = ParseOptions::new().track_positions(true).parse(html);
+    let pos = doc.select("p").get(0).unwrap().source_position().unwrap();
+    assert_eq!((pos.line, pos.column), (4, 1));
+}
+
+#[test]
+fn test_source_positions_implied_tbody() {
+    let html = "<table>\n<tr><td>1</td></tr>\n</table>\n<table>\n<tbody><tr><td>2</td></tr></tbody>\n</table>";
+    let doc = ParseOptions::new().track_positions(true).parse(html);
+    let tbodies = doc.select("tbody");
+    assert_eq!(tbodies.get(0).unwrap().source_position(), None);
+    assert_eq!(tbodies.get(1).unwrap().source_position().unwrap().line, 5);
+}
+
+#[test]
+fn test_source_positions_tags_in_attributes_and_comments() {
+    let html = "<p title=\"<p>\">a</p><!-- <p> --><p>b</p>x<<p>c</p></><p>d</p>";
+    let doc = ParseOptions::new().track_positions(true).parse(html);
+    let offsets: Vec<usize> = doc
+        .select("p")
+        .nodes()
+        .iter()
+        .map(|p| p.source_position().unwrap().offset)
+        .collect();
+    assert_eq!(offsets, vec![0, 32, 42, 53]);
+}
+
+#[test]
+fn test_source_positions_implied_elements() {
+    let html = "<title>t</title>\n<table><tr><td>1</td></tr></table>";
+    let doc = ParseOptions::new().track_positions(true).parse(html);
+    for implied in &["html", "head", "body", "tbody"] {
+        let position = doc.select(implied).get(0).unwrap().source_position();
+        assert_eq!(position, None, "{}", implied);
+    }
+
+    let tr = doc.select("tr").get(0).unwrap().source_position().unwrap();
+    assert_eq!((tr.offset, tr.line, tr.column), (24, 2, 8));
+}
+
+#[test]
+fn test_parse_error_positions() {
+    let html = "<!DOCTYPE html>\n<p>ok</p>\n  <p>bad</b>\n";
+    let doc = ParseOptions::new().track_positions(true).parse(html);
+    let error = &doc.errors[0];
+    assert_eq!(error.offset, Some(html.find("</b>").unwrap()));
+    assert_eq!((error.line, error.column), (3, Some(9)));
+    assert!(error.to_string().starts_with("line 3, column 9: "));
+
+    let doc = Document::from(html);
+    assert_eq!((doc.errors[0].offset, doc.errors[0].column), (None, None));
+}
+
+#[test]
+fn test_source_positions_disabled() {
+    let doc = ParseOptions::new().parse("<p>a</p>");
+    assert_eq!(doc.select("p").get(0).unwrap().source_position(), None);
+}
+
+#[test]
+fn test_fragment_source_positions() {
+    let html = "<div>\n<div>inner</div></div>";
+    let doc = ParseOptions::new()
+        .track_positions(true)
+        .parse_fragment(html, "div");
+    let divs = doc.select("div");
+    assert_eq!(divs.get(0).unwrap().source_position().unwrap().offset, 0);
+    assert_eq!(divs.get(1).unwrap().source_position().unwrap().line, 2);
+}
+
+#[test]
+fn test_parse_error_lines() {
+    let doc = Document::from("<!DOCTYPE html>\n<p>ok</p>\n<p>bad</b>\n");
+    assert!(!doc.errors.is_empty());
+    let error = &doc.errors[0];
+    assert_eq!(error.line, 3);
+    assert!(error.to_string().starts_with("line 3: "));
+}
+
+#[test]
+fn test_parser_feed_byte_by_byte() {
+    let html = format!(
+        r#"<meta charset="Shift_JIS"><ul>{}</ul>"#,
+        "<li>項目</li>".repeat(200)
+    );
+    let (bytes, _, _) = encoding_rs::SHIFT_JIS.encode(&html);
+
+    let mut parser = ParseOptions::new().parser();
+    for byte in bytes.iter() {
+        parser.feed(std::slice::from_ref(byte));
+    }
+    let doc = parser.finish();
+
+    assert_eq!(doc.encoding, encoding_rs::SHIFT_JIS);
+    assert_eq!(doc.select("li").length(), 200);
+    assert_eq!(doc.select("li").last().text().to_string(), "項目");
+}
+
+#[test]
+fn test_parser_with_charset() {
+    let (bytes, _, _) = encoding_rs::GBK.encode("<p>你好</p>");
+    let mut parser = ParseOptions::new().parser_with_charset(Some("gbk"));
+    parser.feed(&bytes);
+    let doc = parser.finish();
+    assert_eq!(doc.encoding, encoding_rs::GBK);
+    assert_eq!(doc.select("p").text().to_string(), "你好");
+}
+
+#[test]
+fn test_parser_guesses_encoding_like_from_bytes() {
+    let ascii = format!("<p>{}</p>", "a".repeat(2000));
+    let late_latin1 = [ascii.as_bytes(), b"<p>caf\xE9</p>"].concat();
+    let late_invalid = [b"<p>caf\xC3\xA9</p>", ascii.as_bytes(), b"<p>\xE9</p>"].concat();
+
+    for (bytes, encoding) in &[
+        (late_latin1, encoding_rs::WINDOWS_1252),
+        (late_invalid, encoding_rs::UTF_8),
+    ] {
+        let doc = Document::from_bytes(bytes);
+        assert_eq!(doc.encoding, *encoding);
+
+        let mut parser = ParseOptions::new().parser();
+        for chunk in bytes.chunks(100) {
+            parser.feed(chunk);
+        }
+        let streamed = parser.finish();
+        assert_eq!(streamed.encoding, *encoding);
+        assert_eq!(streamed.html(), doc.html());
+    }
+}
+
+#[test]
+fn test_parser_empty() {
+    let doc = ParseOptions::new().parser().finish();
+    assert_eq!(doc.select("body").length(), 1);
+}
+
+#[test]
+fn test_parser_source_positions() {
+    let html = format!(
+        "<body>\n{}<p id=\"last\">x</p>",
+        "<div>filler</div>\n".repeat(500)
+    );
+    let mut parser = ParseOptions::new().track_positions(true).parser();
+    for chunk in html.as_bytes().chunks(100) {
+        parser.feed(chunk);
+    }
+    let doc = parser.finish();
+
+    let pos = doc
+        .select("#last")
+        .get(0)
+        .unwrap()
+        .source_position()
+        .unwrap();
+    assert_eq!(pos.line, 502);
+    assert_eq!(&html[pos.offset..pos.offset + 2], "<p");
+    let pos = doc
+        .select("div")
+        .get(250)
+        .unwrap()
+        .source_position()
+        .unwrap();
+    assert_eq!((pos.line, pos.column), (252, 1));
+}
+
+#[test]
+fn test_from_reader_large() {
+    let html = format!("<ul>{}</ul>", "<li>café</li>".repeat(5000));
+    let doc = Document::from_reader(html.as_bytes()).unwrap();
+    assert_eq!(doc.encoding, encoding_rs::UTF_8);
+    assert_eq!(doc.select("li").length(), 5000);
+    assert_eq!(doc.select("li").first().text().to_string(), "café");
+}
@@ -0,0 +1,161 @@
+use nipper::{Document, Matcher, Namespaces};
+
+const RSS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0" xmlns:atom="http://www.w3.org/2005/Atom" xmlns:dc="http://purl.org/dc/elements/1.1/">
+  <channel>
+    <title>Example</title>
+    <atom:link href="https://example.com/feed" rel="self" type="application/rss+xml"/>
+    <item>
+      <title>First</title>
+      <link>https://example.com/1</link>
+      <dc:creator>Alice</dc:creator>
+    </item>
+    <item>
+      <title>Second</title>
+      <link>https://example.com/2</link>
+      <dc:creator>Bob</dc:creator>
+    </item>
+  </channel>
+</rss>"#;
+
+#[test]
+fn test_from_xml_rss() {
+    let doc = Document::from_xml(RSS);
+    let titles: Vec<String> = doc
+        .select("item > title")
+        .iter()
+        .map(|title| title.text().to_string())
+        .collect();
+    assert_eq!(titles, vec!["First", "Second"]);
+
+    // Unlike in HTML, <link> has content and does not close itself.
+    assert_eq!(
+        doc.select("item:nth-of-type(2) link").text().to_string(),
+        "https://example.com/2"
+    );
+}
+
+#[test]
+fn test_from_xml_namespace_prefixes() {
+    let doc = Document::from_xml(RSS);
+    assert_eq!(
+        doc.select("atom|link").attr("href").unwrap().to_string(),
+        "https://example.com/feed"
+    );
+    assert_eq!(doc.select("dc|creator").length(), 2);
+    assert_eq!(
+        doc.select("item dc|creator").last().text().to_string(),
+        "Bob"
+    );
+    assert_eq!(doc.select("*|link").length(), 3);
+}
+
+#[test]
+fn test_from_xml_default_namespace() {
+    let xml = r#"<?xml version="1.0"?>
+<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+  <url><loc>https://example.com/</loc></url>
+  <url><loc>https://example.com/about</loc></url>
+</urlset>"#;
+    let doc = Document::from_xml(xml);
+    assert_eq!(doc.select("url > loc").length(), 2);
+    assert_eq!(
+        doc.select("url:last-child loc").text().to_string(),
+        "https://example.com/about"
+    );
+}
+
+#[test]
+fn test_from_xml_svg() {
+    let xml = r#"<svg:svg xmlns:svg="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink">
+  <svg:rect width="10" height="20"/>
+  <svg:a xlink:href="/target"><svg:circle r="5"/></svg:a>
+</svg:svg>"#;
+    let doc = Document::from_xml(xml);
+    assert_eq!(
+        doc.select("svg|rect").attr("height").unwrap().to_string(),
+        "20"
+    );
+    assert_eq!(doc.select("svg|a > svg|circle").length(), 1);
+    assert_eq!(doc.select("[xlink|href='/target']").length(), 1);
+    assert!(doc.try_select("foo|rect").is_none());
+}
+
+#[test]
+fn test_html_inline_svg_xlink() {
+    let html = r##"<body><svg><use xlink:href="#icon"></use></svg></body>"##;
+    let doc = Document::from(html);
+    assert_eq!(doc.select("[xlink|href]").length(), 1);
+    assert_eq!(doc.select("svg").select("[xlink|href]").length(), 1);
+}
+
+const INLINE: &str = r##"<body>
+  <a href="/home">Home</a>
+  <svg><a xlink:href="#shape"><title>Shape</title><circle r="2"/></a></svg>
+  <math><mi>x</mi></math>
+</body>"##;
+
+#[test]
+fn test_matcher_with_prefixes() {
+    let doc = Document::from(INLINE);
+    let web = Namespaces::web();
+
+    let matcher = Matcher::with_prefixes("svg|a", &web).unwrap();
+    assert_eq!(doc.select_matcher(&matcher).length(), 1);
+    let matcher = Matcher::with_prefixes("html|a", &web).unwrap();
+    assert_eq!(doc.select_matcher(&matcher).text().to_string(), "Home");
+    let matcher = Matcher::with_prefixes("[xlink|href]", &web).unwrap();
+    assert_eq!(doc.select_matcher(&matcher).length(), 1);
+    let matcher = Matcher::with_prefixes("[href]", &web).unwrap();
+    assert_eq!(doc.select_matcher(&matcher).text().to_string(), "Home");
+    let matcher = Matcher::with_prefixes("mathml|mi, svg|title", &web).unwrap();
+    assert_eq!(doc.select_matcher(&matcher).text().to_string(), "Shapex");
+    let matcher = Matcher::with_prefixes("tr:has(svg|a)", &web).unwrap();
+    assert_eq!(doc.select_matcher(&matcher).length(), 0);
+}
+
+#[test]
+fn test_matcher_with_custom_prefixes() {
+    let doc = Document::from(INLINE);
+    let namespaces = Namespaces::new().prefix("s", "http://www.w3.org/2000/svg");
+    let matcher = Matcher::with_prefixes("body s|circle", &namespaces).unwrap();
+    assert_eq!(doc.select_matcher(&matcher).length(), 1);
+
+    let err = Matcher::with_prefixes("svg|circle", &namespaces).unwrap_err();
+    assert_eq!(err.message, "undeclared namespace prefix");
+    assert_eq!(err.token.as_deref(), Some("svg"));
+}
+
+#[test]
+fn test_matcher_with_default_namespace() {
+    let doc = Document::from(INLINE);
+    let namespaces = Namespaces::web().default_namespace("http://www.w3.org/2000/svg");
+
+    let matcher = Matcher::with_prefixes("a", &namespaces).unwrap();
+    assert_eq!(doc.select_matcher(&matcher).length(), 1);
+    let matcher = Matcher::with_prefixes("*|*:has(> a)", &namespaces).unwrap();
+    assert_eq!(
+        doc.select_matcher(&matcher).nodes()[0]
+            .node_name()
+            .unwrap()
+            .to_string(),
+        "svg"
+    );
+    assert_eq!(doc.select_matcher(&matcher).length(), 1);
+    let matcher = Matcher::with_prefixes("*|a", &namespaces).unwrap();
+    assert_eq!(doc.select_matcher(&matcher).length(), 2);
+}
+
+#[test]
+fn test_matcher_has_outside_default_namespace() {
+    let doc = Document::from(r##"<p><svg><a xlink:href="#s"><circle r="1"/></a></svg></p>"##);
+    let namespaces = Namespaces::web().default_namespace("http://www.w3.org/2000/svg");
+
+    for sel in &["html|p:has(svg)", "html|p:has(> svg)", "html|p:has(a)"] {
+        let matcher = Matcher::with_prefixes(sel, &namespaces).unwrap();
+        assert_eq!(doc.select_matcher(&matcher).length(), 1, "{}", sel);
+    }
+
+    let matcher = Matcher::with_prefixes("html|p:has(> a)", &namespaces).unwrap();
+    assert_eq!(doc.select_matcher(&matcher).length(), 0);
+}
@@ -0,0 +1,100 @@
+mod data;
+
+use data::docwiki;
+use html5ever::tree_builder::QuirksMode;
+use nipper::{Document, Matcher, MatcherSet};
+
+#[test]
+fn test_matcher_set_like_select() {
+    let doc = docwiki();
+    let selectors = [
+        "li",
+        "span",
+        "div p",
+        "#content a[href^='/wiki/']",
+        ".mw-headline",
+        "ul > li:nth-child(2n+1)",
+        "h2 + p",
+        "table tr td:first-child",
+        "div#nonexistent span",
+    ];
+
+    let set: MatcherSet<&str> = selectors
+        .iter()
+        .map(|sel| (*sel, Matcher::new(sel).unwrap()))
+        .collect();
+    assert_eq!(set.len(), selectors.len());
+
+    let found = set.select(&doc);
+    for sel in &selectors {
+        let expected: Vec<_> = doc.select(sel).nodes().iter().map(|n| n.id).collect();
+        let actual: Vec<_> = found[sel].nodes().iter().map(|n| n.id).collect();
+        assert_eq!(actual, expected, "{}", sel);
+    }
+}
+
+#[test]
+fn test_matcher_set_shared_keys() {
+    let doc = Document::from(r#"<h1>A</h1><p class="lead">B</p><h2>C</h2><p>D</p>"#);
+
+    let mut set = MatcherSet::new();
+    set.add("headings", Matcher::new("h2").unwrap());
+    set.add("headings", Matcher::new("h1, h2").unwrap());
+    set.add("lead", Matcher::new("p.lead").unwrap());
+    set.add("missing", Matcher::new("table").unwrap());
+
+    let found = set.select(&doc);
+    assert_eq!(found.len(), 3);
+    assert_eq!(found["headings"].text().to_string(), "AC");
+    assert_eq!(found["lead"].text().to_string(), "B");
+    assert!(!found["missing"].exists());
+}
+
+#[test]
+fn test_matcher_set_in_selection() {
+    let doc = Document::from(
+        r#"<div class="a"><p>1</p><div class="b"><p>2</p></div></div><section><p>3</p></section>"#,
+    );
+
+    let set: MatcherSet<usize> = vec![
+        (0, Matcher::new("p").unwrap()),
+        (1, Matcher::new(".a .b p").unwrap()),
+        (2, Matcher::new("section p").unwrap()),
+    ]
+    .into_iter()
+    .collect();
+
+    let found = set.select_in(&doc.select("div"));
+    assert_eq!(found[&0].text().to_string(), "12");
+    assert_eq!(found[&1].text().to_string(), "2");
+    assert!(!found[&2].exists());
+}
+
+#[test]
+fn test_matcher_set_in_document_order() {
+    let doc =
+        Document::from(r#"<ul id="list"><li id="a"><b>a</b></li><li id="b"><b>b</b></li></ul>"#);
+    let items = doc.select("li");
+
+    // Move the first item last, so `items` is no longer in document order.
+    doc.select("#list").append_selection(&doc.select("#a"));
+
+    let set: MatcherSet<usize> = vec![(0, Matcher::new("b").unwrap())].into_iter().collect();
+    let found = set.select_in(&items);
+    assert_eq!(found[&0].text().to_string(), "ba");
+    assert_eq!(items.select("b").text().to_string(), "ba");
+}
+
+#[test]
+fn test_matcher_set_quirks_mode() {
+    let doc = Document::from(r#"<div class="Post"><h2 ID="Title">Title</h2></div>"#);
+    assert_eq!(doc.quirks_mode, QuirksMode::Quirks);
+
+    let mut set = MatcherSet::new();
+    set.add("title", Matcher::new(".post h2").unwrap());
+    set.add("id", Matcher::new("div #title").unwrap());
+
+    let found = set.select(&doc);
+    assert_eq!(found["title"].length(), 1);
+    assert_eq!(found["id"].length(), 1);
+}
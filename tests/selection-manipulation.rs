@@ -1,6 +1,7 @@
 mod data;
 
 use data::doc2;
+use nipper::Document;
 
 #[test]
 fn test_replace_with_html() {
@@ -61,3 +62,32 @@ fn test_replace_with_selection() {
     assert_eq!(doc.select("#nf6").length(), 0);
     assert_eq!(doc.select("#nf5").length(), 1);
 }
+
+#[test]
+fn test_append_selection_moves_attached_node() {
+    let doc =
+        Document::from(r#"<ul id="list"><li id="a">A</li><li id="b">B</li><li id="c">C</li></ul>"#);
+
+    doc.select("#list").append_selection(&doc.select("#a"));
+    let ids: Vec<String> = doc
+        .select("#list > li")
+        .iter()
+        .map(|li| li.attr("id").unwrap().to_string())
+        .collect();
+    assert_eq!(ids, vec!["b", "c", "a"]);
+    assert_eq!(doc.select("li").length(), 3);
+    assert_eq!(doc.select("#a").next_sibling().length(), 0);
+    assert_eq!(
+        doc.select("#c")
+            .next_sibling()
+            .attr("id")
+            .unwrap()
+            .to_string(),
+        "a"
+    );
+    assert_eq!(doc.select("#list").text().to_string(), "BCA");
+
+    // Appending the last child again leaves it in place.
+    doc.select("#list").append_selection(&doc.select("#a"));
+    assert_eq!(doc.select("#list").text().to_string(), "BCA");
+}
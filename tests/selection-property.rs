@@ -2,6 +2,7 @@ mod data;
 
 use data::doc;
 use data::doc2;
+use nipper::Document;
 
 #[test]
 fn test_attr_exists() {
@@ -145,3 +146,38 @@ fn test_remove_class_similar() {
     sel.remove_class("odd");
     assert!(sel.has_class("odder"));
 }
+
+#[test]
+fn test_inner_and_outer_html() {
+    let doc = Document::from(
+        r#"<div id="a"><p>One <b>two</b></p><!-- c --></div><div id="b">Three</div>"#,
+    );
+    let sel = doc.select("div");
+
+    assert_eq!(sel.inner_html().as_ref(), "<p>One <b>two</b></p><!-- c -->");
+    assert_eq!(
+        sel.outer_html().as_ref(),
+        r#"<div id="a"><p>One <b>two</b></p><!-- c --></div>"#
+    );
+    assert_eq!(sel.outer_html(), sel.html());
+
+    let node = sel.nodes()[1].clone();
+    assert_eq!(node.inner_html().as_ref(), "Three");
+    assert_eq!(node.outer_html().as_ref(), r#"<div id="b">Three</div>"#);
+}
+
+#[test]
+fn test_inner_html_raw_text() {
+    let doc = Document::from("<script>if (a < b && c) {}</script>");
+    assert_eq!(
+        doc.select("script").inner_html().as_ref(),
+        "if (a < b && c) {}"
+    );
+}
+
+#[test]
+fn test_inner_html_empty_selection() {
+    let doc = Document::from("<p>Hello</p>");
+    assert_eq!(doc.select("div").inner_html().as_ref(), "");
+    assert_eq!(doc.select("div").outer_html().as_ref(), "");
+}
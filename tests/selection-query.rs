@@ -1,6 +1,7 @@
 mod data;
 
 use data::doc;
+use nipper::{Document, Matcher, Selection, Specificity};
 
 #[test]
 fn test_is() {
@@ -43,3 +44,160 @@ fn test_is_selection_not() {
         "Expected some div NOT to be an anchor."
     );
 }
+
+#[test]
+fn test_matcher_specificity() {
+    let matcher = Matcher::new("#nav > a.active:checked, ul li, [href], *, div:not(.x)").unwrap();
+    assert_eq!(
+        matcher.specificity(),
+        vec![
+            Specificity::new(1, 2, 1),
+            Specificity::new(0, 0, 2),
+            Specificity::new(0, 1, 0),
+            Specificity::new(0, 0, 0),
+            Specificity::new(0, 1, 1),
+        ]
+    );
+
+    let matcher =
+        Matcher::new("tr:has(#x), tr:has(> td.price), li:has(a, #y b), p:not(:has(em))").unwrap();
+    assert_eq!(
+        matcher.specificity(),
+        vec![
+            Specificity::new(1, 0, 1),
+            Specificity::new(0, 1, 2),
+            Specificity::new(1, 0, 2),
+            Specificity::new(0, 0, 2),
+        ]
+    );
+    assert!(Specificity::new(1, 0, 0) > Specificity::new(0, 12, 3));
+    assert!(Specificity::new(0, 1, 2) > Specificity::new(0, 1, 1));
+}
+
+#[test]
+fn test_matcher_matching_selectors() {
+    let doc = Document::from(r#"<ul id="nav"><li class="item"><a href="/">Home</a></li></ul>"#);
+    let matcher = Matcher::new("a, li, #nav a[href], .item > a, p").unwrap();
+    let a = doc.select("a");
+    assert_eq!(matcher.matching_selectors(&a.nodes()[0]), vec![0, 2, 3]);
+    let li = doc.select("li");
+    assert_eq!(matcher.matching_selectors(&li.nodes()[0]), vec![1]);
+}
+
+#[test]
+fn test_matcher_to_css() {
+    for sel in &[
+        "div > p.intro",
+        "#main a[href^=\"http\"], li:nth-child(2n+1)",
+        "tr:has(> td.price)",
+        "p:contains(\"a b\")",
+        "input:checked + label",
+        ":scope ~ h2",
+    ] {
+        let css = Matcher::new(sel).unwrap().to_css();
+        let again = Matcher::new(&css).unwrap();
+        assert_eq!(again.to_css(), css, "{}", sel);
+    }
+
+    assert_eq!(Matcher::new("div>p ,  a").unwrap().to_css(), "div > p, a");
+}
+
+const PAGE: &str = r#"<nav id="nav"><a id="home" href="/">Home</a><a id="about" href="/about">About</a></nav>
+<div id="main">
+  <p id="p1"><a id="first" href="/first">First</a></p>
+  <p id="p2" class="note">No links</p>
+  <a id="last" class="external" href="https://example.com">Last</a>
+</div>"#;
+
+fn ids(sel: &nipper::Selection) -> Vec<String> {
+    sel.iter()
+        .map(|node| node.attr("id").unwrap().to_string())
+        .collect()
+}
+
+#[test]
+fn test_filter() {
+    let doc = Document::from(PAGE);
+    let links = doc.select("a");
+    assert_eq!(
+        ids(&links.filter("[href^='/']")),
+        vec!["home", "about", "first"]
+    );
+    assert_eq!(links.filter("a[").length(), 0);
+
+    let matcher = Matcher::new(".external").unwrap();
+    assert_eq!(ids(&links.filter_matcher(&matcher)), vec!["last"]);
+
+    let nav_links = doc.select("nav a");
+    assert_eq!(
+        ids(&links.filter_selection(&nav_links)),
+        vec!["home", "about"]
+    );
+    assert_eq!(ids(&links.intersection(&nav_links)), vec!["home", "about"]);
+
+    let odd = links.filter_fn(|i, _| i % 2 == 1);
+    assert_eq!(ids(&odd), vec!["about", "last"]);
+    let long = links.filter_fn(|_, sel| sel.text().len() > 4);
+    assert_eq!(ids(&long), vec!["about", "first"]);
+}
+
+#[test]
+fn test_not() {
+    let doc = Document::from(PAGE);
+    let links = doc.select("a");
+    assert_eq!(ids(&links.not("nav a")), vec!["first", "last"]);
+    assert_eq!(links.not("a[").length(), 4);
+
+    let matcher = Matcher::new("[href^='/']").unwrap();
+    assert_eq!(ids(&links.not_matcher(&matcher)), vec!["last"]);
+
+    let nav_links = doc.select("#nav").children();
+    assert_eq!(ids(&links.not_selection(&nav_links)), vec!["first", "last"]);
+    assert_eq!(ids(&links.not_fn(|i, _| i < 3)), vec!["last"]);
+}
+
+#[test]
+fn test_has() {
+    let doc = Document::from(PAGE);
+    let paragraphs = doc.select("p");
+    assert_eq!(ids(&paragraphs.has("a")), vec!["p1"]);
+    assert_eq!(ids(&doc.select("div, nav").has("a.external")), vec!["main"]);
+    assert_eq!(paragraphs.has("a[").length(), 0);
+
+    let matcher = Matcher::new("[href]").unwrap();
+    assert_eq!(
+        ids(&doc.select("nav, div, p").has_matcher(&matcher)),
+        vec!["nav", "main", "p1"]
+    );
+
+    let first = doc.select("#first");
+    assert_eq!(
+        ids(&doc.select("div, p").has_selection(&first)),
+        vec!["main", "p1"]
+    );
+    assert_eq!(doc.select("#first").has_selection(&first).length(), 0);
+}
+
+#[test]
+fn test_add_and_union() {
+    let doc = Document::from(PAGE);
+    let last = doc.select("#last");
+    assert_eq!(ids(&last.add("nav a")), vec!["home", "about", "last"]);
+    assert_eq!(ids(&last.add("a[")), vec!["last"]);
+    assert_eq!(Selection::default().add("a").length(), 0);
+    // An empty selection has no document to add from.
+    assert_eq!(doc.select("blink").add("nav a").length(), 0);
+
+    let matcher = Matcher::new(".note").unwrap();
+    assert_eq!(ids(&last.add_matcher(&matcher)), vec!["p2", "last"]);
+
+    let paragraphs = doc.select("p");
+    assert_eq!(
+        ids(&last.add_selection(&paragraphs)),
+        vec!["p1", "p2", "last"]
+    );
+    assert_eq!(
+        ids(&paragraphs.union(&doc.select("#p2, #nav"))),
+        vec!["nav", "p1", "p2"]
+    );
+}
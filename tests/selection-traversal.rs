@@ -2,7 +2,10 @@ mod data;
 
 use data::doc;
 use data::docwiki;
+use html5ever::tree_builder::QuirksMode;
 use nipper::Document;
+use nipper::Matcher;
+use std::cmp::Ordering;
 
 #[test]
 fn test_select() {
@@ -120,3 +123,462 @@ fn test_nth_child() {
 
     assert!(a.length() == 1);
 }
+
+#[test]
+fn test_select_checked() {
+    let doc = doc();
+    let sel = doc.select_checked("div.hero-unit").unwrap();
+    assert_eq!(sel.length(), 1);
+    assert_eq!(sel.select_checked("h1").unwrap().length(), 1);
+    assert_eq!(doc.select_checked("zz").unwrap().length(), 0);
+}
+
+#[test]
+fn test_select_checked_errors() {
+    let doc = doc();
+
+    let err = doc.select_checked("div > > p").unwrap_err();
+    assert_eq!(err.message, "dangling combinator");
+    assert_eq!(err.token, None);
+    assert_eq!(err.column, 7);
+    assert_eq!(err.to_string(), "dangling combinator at column 7");
+
+    let err = doc.select_checked(":+ ^").unwrap_err();
+    assert_eq!(err.token.as_deref(), Some("+"));
+    assert_eq!(
+        err.to_string(),
+        "expected a pseudo-class or pseudo-element name `+` at column 3"
+    );
+
+    let err = doc.select_checked("a:hovr").unwrap_err();
+    assert_eq!(err.message, "unsupported pseudo-class or pseudo-element");
+    assert_eq!(err.token.as_deref(), Some("hovr"));
+
+    let err = doc.select_checked("p[").unwrap_err();
+    assert_eq!(err.message, "unexpected end of selector");
+
+    let err = doc.select("div.hero-unit").select_checked("").unwrap_err();
+    assert_eq!(err.column, 1);
+}
+
+#[test]
+fn test_matcher_error() {
+    let err = Matcher::new("ü ü[").unwrap_err();
+    assert_eq!(err.column, 5);
+    assert!(Matcher::new("svg|rect").is_err());
+}
+
+#[test]
+fn test_select_after_mutation() {
+    let doc = Document::from(r#"<div id="a" class="box"><p>One</p></div><div id="b"></div>"#);
+    assert_eq!(doc.select("#a").length(), 1);
+    assert_eq!(doc.select("p").length(), 1);
+
+    doc.select("#a").set_attr("id", "c");
+    assert_eq!(doc.select("#a").length(), 0);
+    assert_eq!(doc.select("#c").length(), 1);
+
+    doc.select("#b").add_class("box wide");
+    assert_eq!(doc.select(".box").length(), 2);
+    assert_eq!(doc.select(".wide").attr("id").unwrap().to_string(), "b");
+
+    doc.select("#b").append_html("<p>Two</p>");
+    let text: Vec<String> = doc
+        .select("p")
+        .iter()
+        .map(|p| p.text().to_string())
+        .collect();
+    assert_eq!(text, vec!["One", "Two"]);
+
+    doc.select("#c").remove();
+    assert_eq!(doc.select("p").text().to_string(), "Two");
+    assert_eq!(doc.select(".box").length(), 1);
+}
+
+#[test]
+fn test_select_group_in_document_order() {
+    let doc =
+        Document::from(r#"<p class="x">1</p><span id="y">2</span><p>3</p><b class="x">4</b>"#);
+    let sel = doc.select(".x, #y, p");
+    let text: Vec<String> = sel.iter().map(|n| n.text().to_string()).collect();
+    assert_eq!(text, vec!["1", "2", "3", "4"]);
+}
+
+#[test]
+fn test_select_indexed_in_selection() {
+    let doc = Document::from(
+        r#"<ul id="a"><li class="x">1</li><li>2</li></ul><ul id="b"><li class="x">3</li></ul>"#,
+    );
+    assert_eq!(doc.select("#b").select(".x").text().to_string(), "3");
+    assert_eq!(doc.select("#a").select("li").length(), 2);
+    assert_eq!(doc.select("li.x").select(".x").length(), 0);
+}
+
+#[test]
+fn test_select_mixed_case_names() {
+    let doc = Document::from(r#"<svg><foreignObject></foreignObject></svg><DIV></DIV>"#);
+    assert_eq!(doc.select("foreignObject").length(), 1);
+    assert_eq!(doc.select("div").length(), 1);
+    assert_eq!(doc.select("DIV").length(), 1);
+}
+
+const NESTED: &str = r#"<div id="outer">
+  <ul id="list">
+    <li id="one">One <ul id="inner-list"><li id="inner">Inner</li></ul></li>
+    <li id="two">Two</li>
+  </ul>
+  <h2 id="h1">A</h2>
+  <p id="p1">B</p>
+  <h2 id="h2">C</h2>
+</div>"#;
+
+fn ids(sel: &nipper::Selection) -> Vec<String> {
+    sel.iter()
+        .map(|node| node.attr("id").unwrap().to_string())
+        .collect()
+}
+
+#[test]
+fn test_select_leading_combinators() {
+    let doc = Document::from(NESTED);
+    let list = doc.select("#list");
+    assert_eq!(ids(&list.select("li")), vec!["one", "inner", "two"]);
+    assert_eq!(ids(&list.select("> li")), vec!["one", "two"]);
+    assert_eq!(ids(&list.select("+ h2")), vec!["h1"]);
+    assert_eq!(ids(&list.select("~ h2")), vec!["h1", "h2"]);
+    assert_eq!(ids(&list.select("~ p, > li")), vec!["one", "two", "p1"]);
+    assert_eq!(ids(&doc.select("li").select("> ul > li")), vec!["inner"]);
+}
+
+#[test]
+fn test_select_scope() {
+    let doc = Document::from(NESTED);
+    let list = doc.select("#list");
+    assert_eq!(ids(&list.select(":scope > li")), vec!["one", "two"]);
+    assert_eq!(ids(&list.select("div li")), vec!["one", "inner", "two"]);
+    assert_eq!(ids(&list.select(":scope div li")), Vec::<String>::new());
+    assert_eq!(ids(&list.select(":scope li li")), vec!["inner"]);
+    assert_eq!(
+        ids(&doc.select("li").select(":scope > ul > li")),
+        vec!["inner"]
+    );
+}
+
+#[test]
+fn test_select_relative_errors() {
+    let doc = Document::from(NESTED);
+    let list = doc.select("#list");
+    let err = list.select_checked("> li[").unwrap_err();
+    assert_eq!(err.column, 6);
+    assert!(list.select_checked("> > li").is_err());
+    assert!(list.select_checked("li, >").is_err());
+}
+
+const LEGACY: &str = r#"<html><body><p class="Big" id="Main">Legacy</p></body></html>"#;
+
+#[test]
+fn test_quirks_mode_ids_and_classes() {
+    let doc = Document::from(LEGACY);
+    assert_eq!(doc.quirks_mode, QuirksMode::Quirks);
+    assert_eq!(doc.select(".big").length(), 1);
+    assert_eq!(doc.select("#main").length(), 1);
+    assert_eq!(doc.select("body .BIG").length(), 1);
+    assert_eq!(doc.select("body").select("#MAIN").length(), 1);
+    assert!(doc.select("p").is(".big"));
+
+    let doc = Document::from(&format!("<!DOCTYPE html>{}", LEGACY));
+    assert_eq!(doc.quirks_mode, QuirksMode::NoQuirks);
+    assert_eq!(doc.select(".big").length(), 0);
+    assert_eq!(doc.select("#main").length(), 0);
+    assert_eq!(doc.select(".Big").length(), 1);
+}
+
+#[test]
+fn test_attribute_case_flags() {
+    let doc = Document::from(
+        r#"<!DOCTYPE html><input id="a" name="Foo" type="TEXT"><input id="b" name="foo" type="text">"#,
+    );
+    assert_eq!(doc.select("[name=foo i]").length(), 2);
+    assert_eq!(
+        doc.select("[name=foo s]").attr("id").unwrap().to_string(),
+        "b"
+    );
+    // `type` is compared case-insensitively in HTML unless `s` is given.
+    assert_eq!(doc.select("[type=text]").length(), 2);
+    assert_eq!(
+        doc.select("[type=text s]").attr("id").unwrap().to_string(),
+        "b"
+    );
+}
+
+fn names(sel: &nipper::Selection) -> Vec<String> {
+    sel.iter()
+        .map(|node| match node.attr("id") {
+            Some(id) => id.to_string(),
+            None => node.nodes()[0].node_name().unwrap().to_string(),
+        })
+        .collect()
+}
+
+#[test]
+fn test_closest() {
+    let doc = Document::from(NESTED);
+    let items = doc.select("li");
+    assert_eq!(ids(&items.closest("ul")), vec!["list", "inner-list"]);
+    assert_eq!(ids(&items.closest("li")), vec!["one", "inner", "two"]);
+    assert_eq!(ids(&items.closest("div")), vec!["outer"]);
+    assert_eq!(items.closest("table").length(), 0);
+    assert_eq!(items.closest("li[").length(), 0);
+
+    let matcher = Matcher::new("#list").unwrap();
+    assert_eq!(
+        ids(&doc.select("#inner").closest_matcher(&matcher)),
+        vec!["list"]
+    );
+
+    let lists = doc.select("ul");
+    assert_eq!(
+        ids(&items.closest_selection(&lists)),
+        vec!["list", "inner-list"]
+    );
+}
+
+#[test]
+fn test_parents() {
+    let doc = Document::from(NESTED);
+    assert_eq!(
+        names(&doc.select("#inner").parents()),
+        vec!["html", "body", "outer", "list", "one", "inner-list"]
+    );
+    assert_eq!(
+        names(&doc.select("#inner, #two").parents()),
+        vec!["html", "body", "outer", "list", "one", "inner-list"]
+    );
+    assert_eq!(
+        ids(&doc.select("#inner").parents_filtered("ul")),
+        vec!["list", "inner-list"]
+    );
+    assert_eq!(doc.select("#inner").parents_filtered("ul[").length(), 0);
+    assert_eq!(doc.select("html").parents().length(), 0);
+
+    let matcher = Matcher::new("li").unwrap();
+    assert_eq!(
+        ids(&doc.select("#inner").parents_filtered_matcher(&matcher)),
+        vec!["one"]
+    );
+
+    let divs = doc.select("div");
+    assert_eq!(
+        ids(&doc.select("li").parents_filtered_selection(&divs)),
+        vec!["outer"]
+    );
+}
+
+#[test]
+fn test_parents_until() {
+    let doc = Document::from(NESTED);
+    let inner = doc.select("#inner");
+    assert_eq!(
+        ids(&inner.parents_until("#list")),
+        vec!["one", "inner-list"]
+    );
+    assert_eq!(
+        ids(&doc.select("li").parents_until("div")),
+        vec!["list", "one", "inner-list"]
+    );
+    assert_eq!(inner.parents_until("ul[").length(), 6);
+
+    let matcher = Matcher::new("li").unwrap();
+    assert_eq!(
+        ids(&inner.parents_until_matcher(&matcher)),
+        vec!["inner-list"]
+    );
+
+    let outer = doc.select("#outer");
+    assert_eq!(
+        ids(&inner.parents_until_selection(&outer)),
+        vec!["list", "one", "inner-list"]
+    );
+}
+
+const DEFINITIONS: &str = r#"<dl id="terms">
+  <dt id="t1">Rust</dt>
+  <dd id="d1">A language</dd>
+  <dd id="d2">A fungus</dd>
+  <dt id="t2">Go</dt>
+  <dd id="d3">A language</dd>
+  <dt id="t3">Zig</dt>
+</dl>"#;
+
+#[test]
+fn test_next_all_and_prev_all() {
+    let doc = Document::from(DEFINITIONS);
+    assert_eq!(ids(&doc.select("#t2").next_all()), vec!["d3", "t3"]);
+    assert_eq!(ids(&doc.select("#t2").prev_all()), vec!["t1", "d1", "d2"]);
+    assert_eq!(
+        ids(&doc.select("#t1, #d2").next_all()),
+        vec!["d1", "d2", "t2", "d3", "t3"]
+    );
+    assert_eq!(
+        ids(&doc.select("#t1").next_all_filtered("dt")),
+        vec!["t2", "t3"]
+    );
+    assert_eq!(doc.select("#t1").next_all_filtered("dt[").length(), 0);
+    assert_eq!(doc.select("#t3").next_all().length(), 0);
+
+    let matcher = Matcher::new("dd").unwrap();
+    assert_eq!(
+        ids(&doc.select("#t3").prev_all_filtered_matcher(&matcher)),
+        vec!["d1", "d2", "d3"]
+    );
+    assert_eq!(
+        ids(&doc.select("#t1").next_all_filtered_matcher(&matcher)),
+        vec!["d1", "d2", "d3"]
+    );
+
+    let languages = doc.select("#d1, #d3");
+    assert_eq!(
+        ids(&doc.select("#t1").next_all_filtered_selection(&languages)),
+        vec!["d1", "d3"]
+    );
+    assert_eq!(
+        ids(&doc.select("#t3").prev_all_filtered_selection(&languages)),
+        vec!["d1", "d3"]
+    );
+    assert_eq!(
+        ids(&doc.select("#t3").prev_all_filtered("dt")),
+        vec!["t1", "t2"]
+    );
+}
+
+#[test]
+fn test_siblings() {
+    let doc = Document::from(DEFINITIONS);
+    assert_eq!(
+        ids(&doc.select("#d2").siblings()),
+        vec!["t1", "d1", "t2", "d3", "t3"]
+    );
+    assert_eq!(
+        ids(&doc.select("dt").siblings_filtered("dt")),
+        vec!["t1", "t2", "t3"]
+    );
+    assert_eq!(doc.select("#d2").siblings_filtered("dt[").length(), 0);
+    assert_eq!(doc.select("html").siblings().length(), 0);
+
+    let matcher = Matcher::new("dd").unwrap();
+    assert_eq!(
+        ids(&doc.select("#t2").siblings_filtered_matcher(&matcher)),
+        vec!["d1", "d2", "d3"]
+    );
+
+    let terms = doc.select("dt");
+    assert_eq!(
+        ids(&doc.select("#d1").siblings_filtered_selection(&terms)),
+        vec!["t1", "t2", "t3"]
+    );
+}
+
+#[test]
+fn test_next_until_and_prev_until() {
+    let doc = Document::from(DEFINITIONS);
+    let terms = doc.select("dt");
+    assert_eq!(ids(&terms.next_until("dt")), vec!["d1", "d2", "d3"]);
+    assert_eq!(
+        ids(&doc.select("#d3").prev_until("#t1")),
+        vec!["d1", "d2", "t2"]
+    );
+    assert_eq!(ids(&doc.select("#t2").next_until("dt[")), vec!["d3", "t3"]);
+    assert_eq!(
+        ids(&doc.select("#t2").prev_until("dt[")),
+        vec!["t1", "d1", "d2"]
+    );
+
+    let matcher = Matcher::new("dt").unwrap();
+    assert_eq!(
+        ids(&doc.select("#t1").next_until_matcher(&matcher)),
+        vec!["d1", "d2"]
+    );
+    assert_eq!(
+        ids(&doc.select("#d2").prev_until_matcher(&matcher)),
+        vec!["d1"]
+    );
+
+    let stop = doc.select("#d2");
+    assert_eq!(
+        ids(&doc.select("#t1").next_until_selection(&stop)),
+        vec!["d1"]
+    );
+    assert_eq!(
+        ids(&doc.select("#t3").prev_until_selection(&stop)),
+        vec!["t2", "d3"]
+    );
+}
+
+#[test]
+fn test_compare_document_order() {
+    let doc = Document::from(DEFINITIONS);
+    let t1 = doc.select("#t1").nodes()[0].clone();
+    let t3 = doc.select("#t3").nodes()[0].clone();
+    let tree = t1.tree;
+    assert_eq!(tree.compare_document_order(&t1.id, &t3.id), Ordering::Less);
+    assert_eq!(
+        tree.compare_document_order(&t3.id, &t1.id),
+        Ordering::Greater
+    );
+    assert_eq!(tree.compare_document_order(&t1.id, &t1.id), Ordering::Equal);
+    assert!(tree.document_position(&t1.id) < tree.document_position(&t3.id));
+
+    // Moving a node updates the order.
+    doc.select("#terms").append_selection(&doc.select("#t1"));
+    assert_eq!(
+        tree.compare_document_order(&t1.id, &t3.id),
+        Ordering::Greater
+    );
+
+    // Detached nodes come last.
+    t3.remove_from_parent();
+    assert_eq!(tree.document_position(&t3.id), None);
+    assert_eq!(
+        tree.compare_document_order(&t3.id, &t1.id),
+        Ordering::Greater
+    );
+}
+
+#[test]
+fn test_traversal_in_document_order() {
+    let doc = Document::from(NESTED);
+    let items = doc.select("#one, #two, #inner");
+    assert_eq!(ids(&items), vec!["one", "inner", "two"]);
+
+    // Move the first list item last, so `items` is no longer in document
+    // order.
+    doc.select("#list").append_selection(&doc.select("#one"));
+    assert_eq!(ids(&items.parent()), vec!["list", "inner-list"]);
+    assert_eq!(ids(&items.closest("li")), vec!["two", "one", "inner"]);
+    assert_eq!(ids(&items.filter("li")), vec!["two", "one", "inner"]);
+    assert_eq!(ids(&items.not("#inner")), vec!["two", "one"]);
+    assert_eq!(ids(&items.select("li")), vec!["inner"]);
+    assert_eq!(ids(&items.prev_sibling()), vec!["two"]);
+    assert_eq!(
+        ids(&items.union(&doc.select("#outer"))),
+        vec!["outer", "two", "one", "inner"]
+    );
+}
+
+#[test]
+fn test_select_only_elements() {
+    let doc = Document::from("<ul> <li>A</li> text <!-- note --> <li>B</li></ul>");
+    let names = |sel: &str| -> Vec<String> {
+        doc.select(sel)
+            .nodes()
+            .iter()
+            .map(|node| node.node_name().unwrap().to_string())
+            .collect()
+    };
+
+    assert_eq!(names("ul > *"), vec!["li", "li"]);
+    assert_eq!(names("ul > :not(li)"), Vec::<String>::new());
+    assert_eq!(names("ul > :first-child"), vec!["li"]);
+    assert_eq!(names("*"), vec!["html", "head", "body", "ul", "li", "li"]);
+    assert_eq!(doc.select("ul").select("*").length(), 2);
+}
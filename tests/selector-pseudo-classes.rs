@@ -0,0 +1,326 @@
+use nipper::Document;
+
+const FORM: &str = r#"<form>
+  <input id="name" type="text" required placeholder="Name">
+  <input id="email" type="email" value="a@example.com" placeholder="Email">
+  <input id="agree" type="checkbox" checked>
+  <input id="news" type="CHECKBOX">
+  <input id="locked" type="text" readonly>
+  <input id="off" type="text" disabled>
+  <select id="size" required>
+    <option id="s">S</option>
+    <option id="m" selected>M</option>
+    <optgroup disabled><option id="xl">XL</option></optgroup>
+  </select>
+  <textarea id="notes" placeholder="Notes"></textarea>
+  <fieldset disabled>
+    <legend><input id="in-legend"></legend>
+    <input id="in-fieldset">
+  </fieldset>
+  <div id="editor" contenteditable><p id="para">Edit me</p></div>
+</form>
+<a id="home" href="/">Home</a>
+<a id="anchor" name="top">Top</a>
+<link id="style" rel="stylesheet" href="a.css">"#;
+
+fn ids(doc: &Document, sel: &str) -> Vec<String> {
+    doc.select(sel)
+        .iter()
+        .map(|node| node.attr("id").unwrap().to_string())
+        .collect()
+}
+
+#[test]
+fn test_checked() {
+    let doc = Document::from(FORM);
+    assert_eq!(ids(&doc, ":checked"), vec!["agree", "m"]);
+    assert_eq!(
+        ids(&doc, "input:not(:checked)[type=checkbox i]"),
+        vec!["news"]
+    );
+}
+
+#[test]
+fn test_checked_default_option() {
+    let doc = Document::from(
+        r#"<select><option id="a" disabled>A</option><option id="b">B</option><option id="c">C</option></select>
+        <select><optgroup><option id="d" selected>D</option></optgroup><option id="e" selected>E</option></select>
+        <select multiple><option id="f">F</option><option id="g" selected>G</option></select>
+        <select size="3"><option id="h">H</option></select>
+        <option id="i">I</option>"#,
+    );
+    assert_eq!(ids(&doc, "option:checked"), vec!["b", "e", "g"]);
+}
+
+#[test]
+fn test_disabled_and_enabled() {
+    let doc = Document::from(FORM);
+    assert_eq!(
+        ids(&doc, "input:disabled, option:disabled"),
+        vec!["off", "xl", "in-fieldset"]
+    );
+    assert_eq!(
+        ids(&doc, "input:enabled"),
+        vec!["name", "email", "agree", "news", "locked", "in-legend"]
+    );
+}
+
+#[test]
+fn test_required_and_optional() {
+    let doc = Document::from(FORM);
+    assert_eq!(ids(&doc, ":required"), vec!["name", "size"]);
+    assert_eq!(doc.select(":optional").length(), 8);
+    assert!(!doc.select("fieldset").is(":optional"));
+}
+
+#[test]
+fn test_read_only_and_read_write() {
+    let doc = Document::from(FORM);
+    assert_eq!(
+        ids(&doc, ":read-write"),
+        vec!["name", "email", "notes", "in-legend", "editor", "para"]
+    );
+    assert!(doc.select("#locked").is(":read-only"));
+    assert!(doc.select("#off").is(":read-only"));
+    assert!(doc.select("#agree").is(":read-only"));
+}
+
+#[test]
+fn test_links() {
+    let doc = Document::from(FORM);
+    assert_eq!(ids(&doc, ":link"), vec!["home"]);
+    assert_eq!(ids(&doc, ":any-link"), vec!["home"]);
+}
+
+#[test]
+fn test_placeholder_shown() {
+    let doc = Document::from(FORM);
+    assert_eq!(ids(&doc, ":placeholder-shown"), vec!["name", "notes"]);
+}
+
+#[test]
+fn test_unknown_pseudo_class() {
+    let doc = Document::from(FORM);
+    let err = doc.select_checked(":hover").unwrap_err();
+    assert_eq!(err.token.as_deref(), Some("hover"));
+}
+
+const PRODUCTS: &str = r#"<table>
+  <tr id="r1"><th>Price</th><td>$10</td></tr>
+  <tr id="r2"><th>Weight</th><td>2 kg <span>(Price per kg: $5)</span></td></tr>
+  <tr id="r3"><th>price</th><td>n/a</td></tr>
+</table>"#;
+
+#[test]
+fn test_contains() {
+    let doc = Document::from(PRODUCTS);
+    assert_eq!(ids(&doc, r#"tr:contains("Price")"#), vec!["r1", "r2"]);
+    assert_eq!(ids(&doc, "tr:contains(price)"), vec!["r3"]);
+    assert_eq!(doc.select(r#"td:contains("$")"#).length(), 2);
+}
+
+#[test]
+fn test_contains_own() {
+    let doc = Document::from(PRODUCTS);
+    assert_eq!(doc.select(r#"td:containsOwn("Price")"#).length(), 0);
+    assert_eq!(
+        doc.select(r#"td:containsOwn("kg")"#).text().as_ref(),
+        "2 kg (Price per kg: $5)"
+    );
+    assert_eq!(doc.select(r#"span:containsOwn("Price")"#).length(), 1);
+}
+
+#[test]
+fn test_contains_invalid() {
+    let doc = Document::from(PRODUCTS);
+    assert!(doc.select_checked("td:contains()").is_err());
+    assert!(doc.select_checked("td:contains(a, b)").is_err());
+}
+
+#[cfg(feature = "regex")]
+#[test]
+fn test_matches() {
+    let doc = Document::from(PRODUCTS);
+    assert_eq!(
+        ids(&doc, r#"tr:matches("(?i)^\s*price")"#),
+        vec!["r1", "r3"]
+    );
+    assert_eq!(doc.select(r"td:matches(^\$\d+$)").text().as_ref(), "$10");
+    assert_eq!(doc.select(r"td:matchesOwn(\$)").text().as_ref(), "$10");
+}
+
+#[cfg(feature = "regex")]
+#[test]
+fn test_matches_invalid_regex() {
+    let doc = Document::from(PRODUCTS);
+    let err = doc
+        .select_checked(r#"td:matches("(unclosed")"#)
+        .unwrap_err();
+    assert_eq!(err.message, "invalid regular expression");
+    assert_eq!(err.token.as_deref(), Some("(unclosed"));
+}
+
+#[cfg(not(feature = "regex"))]
+#[test]
+fn test_matches_needs_feature() {
+    let doc = Document::from(PRODUCTS);
+    assert!(doc.select_checked("td:matches(x)").is_err());
+}
+
+const ROWS: &str = r#"<table>
+  <tr id="a"><td class="name">A</td><td class="price">1</td></tr>
+  <tr id="b"><td class="name">B</td><td><span class="price">2</span></td></tr>
+  <tr id="c" class="sale"><td class="name">C</td></tr>
+  <tr id="d"><td class="name">D</td></tr>
+</table>
+<section id="s1"><h2>One</h2><div><p>text</p></div></section>
+<section id="s2"><p>text</p></section>"#;
+
+#[test]
+fn test_has_descendant() {
+    let doc = Document::from(ROWS);
+    assert_eq!(ids(&doc, "tr:has(.price)"), vec!["a", "b"]);
+    assert_eq!(ids(&doc, "section:has(div p)"), vec!["s1"]);
+    assert_eq!(ids(&doc, "tr:has(span, .sale)"), vec!["b"]);
+}
+
+#[test]
+fn test_has_child() {
+    let doc = Document::from(ROWS);
+    assert_eq!(ids(&doc, "tr:has(> td.price)"), vec!["a"]);
+    assert_eq!(ids(&doc, "section:has(> p)"), vec!["s2"]);
+}
+
+#[test]
+fn test_has_sibling() {
+    let doc = Document::from(ROWS);
+    assert_eq!(ids(&doc, "tr:has(+ .sale)"), vec!["b"]);
+    assert_eq!(ids(&doc, "tr:has(~ .sale)"), vec!["a", "b"]);
+    assert_eq!(ids(&doc, "tr:has(+ tr > td > .price)"), vec!["a"]);
+    assert_eq!(doc.select("tr:has(+ tr > .price)").length(), 0);
+}
+
+#[test]
+fn test_has_nested() {
+    let doc = Document::from(ROWS);
+    assert_eq!(ids(&doc, "tr:not(:has(.price))"), vec!["c", "d"]);
+    assert_eq!(doc.select("table:has(tr:has(> .price))").length(), 1);
+    assert_eq!(ids(&doc, r#"tr:has(td:contains("C"))"#), vec!["c"]);
+}
+
+#[test]
+fn test_has_invalid() {
+    let doc = Document::from(ROWS);
+
+    let err = doc.select_checked("tr:has()").unwrap_err();
+    assert_eq!(err.message, "empty selector");
+
+    let err = doc.select_checked("tr:has(> > td)").unwrap_err();
+    assert_eq!(err.message, "dangling combinator");
+    assert_eq!(err.column, 10);
+}
+
+const LISTS: &str = r#"<ul id="a">
+  <li id="a1"><a id="a1-link" href="/a1">A1</a></li>
+  <li id="a2"><a id="a2-link" href="/a2">A2</a></li>
+  <li id="a3"><a id="a3-link" href="/a3">A3</a></li>
+</ul>
+<ul id="b">
+  <li id="b1"><a id="b1-link" href="/b1">B1</a></li>
+  <li id="b2"><a id="b2-link" href="/b2">B2</a></li>
+</ul>"#;
+
+#[test]
+fn test_eq_gt_lt() {
+    let doc = Document::from(LISTS);
+    assert_eq!(ids(&doc, "li:eq(2)"), vec!["a3"]);
+    assert_eq!(ids(&doc, "li:eq(-1)"), vec!["b2"]);
+    assert_eq!(ids(&doc, "li:eq(9)"), Vec::<String>::new());
+    assert_eq!(ids(&doc, "li:gt(2)"), vec!["b1", "b2"]);
+    assert_eq!(ids(&doc, "li:lt(2)"), vec!["a1", "a2"]);
+    assert_eq!(ids(&doc, "li:gt(0):lt(1)"), vec!["a2"]);
+}
+
+#[test]
+fn test_first_last_even_odd() {
+    let doc = Document::from(LISTS);
+    assert_eq!(ids(&doc, "li:first"), vec!["a1"]);
+    assert_eq!(ids(&doc, "li:last"), vec!["b2"]);
+    assert_eq!(ids(&doc, "li:even"), vec!["a1", "a3", "b2"]);
+    assert_eq!(ids(&doc, "li:odd"), vec!["a2", "b1"]);
+    assert_eq!(ids(&doc, "ul > :first"), vec!["a1"]);
+}
+
+#[test]
+fn test_positional_steps() {
+    let doc = Document::from(LISTS);
+    assert_eq!(ids(&doc, "ul:last li"), vec!["b1", "b2"]);
+    assert_eq!(ids(&doc, "ul:last > li:first a"), vec!["b1-link"]);
+    assert_eq!(ids(&doc, "li:eq(1) + li"), vec!["a3"]);
+    assert_eq!(ids(&doc, "li:first, li:last"), vec!["a1", "b2"]);
+    assert_eq!(ids(&doc, "li:last, ul:first li:first"), vec!["a1", "b2"]);
+}
+
+#[test]
+fn test_positional_whitespace() {
+    let doc = Document::from(LISTS);
+    assert_eq!(ids(&doc, "li:first "), vec!["a1"]);
+    assert_eq!(ids(&doc, " li:last\n"), vec!["b2"]);
+    assert_eq!(ids(&doc, "li:first , ul:last"), vec!["a1", "b"]);
+    assert_eq!(ids(&doc, "li:first ,li:last "), vec!["a1", "b2"]);
+    assert_eq!(
+        ids(&doc, "ul li , li:eq(1)"),
+        vec!["a1", "a2", "a3", "b1", "b2"]
+    );
+    assert_eq!(ids(&doc, "ul:last  >  li:first  a "), vec!["b1-link"]);
+}
+
+#[test]
+fn test_positional_in_selection() {
+    let doc = Document::from(LISTS);
+    let lists = doc.select("ul");
+    let items = lists.select("li:last");
+    assert_eq!(items.attr("id").unwrap().to_string(), "b2");
+    assert_eq!(lists.select("li:odd").length(), 2);
+    assert_eq!(
+        doc.select("#b")
+            .select(":first")
+            .attr("id")
+            .unwrap()
+            .to_string(),
+        "b1"
+    );
+}
+
+#[test]
+fn test_positional_relative_to_selection() {
+    let doc = Document::from(LISTS);
+    let lists = doc.select("ul");
+    let select_ids = |sel: &str| -> Vec<String> {
+        lists
+            .select(sel)
+            .iter()
+            .map(|node| node.attr("id").unwrap().to_string())
+            .collect()
+    };
+
+    assert_eq!(select_ids("> li:first"), vec!["a1"]);
+    assert_eq!(select_ids(":scope > li:last"), vec!["b2"]);
+    assert_eq!(select_ids("> li:eq(1) > a"), vec!["a2-link"]);
+    assert_eq!(select_ids("> li:first, > li:last"), vec!["a1", "b2"]);
+    assert_eq!(select_ids("+ ul:first > li"), vec!["b1", "b2"]);
+    assert_eq!(select_ids("> li:gt(3) a"), vec!["b2-link"]);
+
+    let err = lists.select_checked("> li:eq(a)").unwrap_err();
+    assert_eq!(err.column, 9);
+    assert!(lists.select_checked("li:has(> a:first)").is_err());
+}
+
+#[test]
+fn test_invalid_positional() {
+    let doc = Document::from(LISTS);
+    let err = doc.select_checked("li:eq(a)").unwrap_err();
+    assert_eq!(err.column, 7);
+    assert!(doc.select_checked("li:not(:first)").is_err());
+    assert!(doc.select_checked("li::first").is_err());
+}
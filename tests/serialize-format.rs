@@ -0,0 +1,109 @@
+use nipper::{Document, SerializeOptions};
+
+const PAGE: &str = r#"<!DOCTYPE html>
+<html><head><title>Example</title>
+<style>
+  body { color: red; }
+</style></head>
+<body>
+  <!-- navigation -->
+  <nav><ul><li><a href="/">Home</a></li>
+  <li><a href="/about" class="active">About   <em>us</em></a></li></ul></nav>
+  <div id="main">
+    Hello,   <b>world</b>!
+    <pre>  keep
+    this  </pre>
+    <p></p>
+  </div>
+</body></html>"#;
+
+#[test]
+fn test_default_options_preserve_markup() {
+    let doc = Document::from(PAGE);
+    assert_eq!(doc.html_with(&SerializeOptions::new()), doc.html());
+}
+
+#[test]
+fn test_pretty() {
+    let doc = Document::from(PAGE);
+    let expected = r#"<!DOCTYPE html>
+<html>
+  <head>
+    <title>Example</title>
+    <style>
+  body { color: red; }
+</style>
+  </head>
+  <body>
+    <!-- navigation -->
+    <nav>
+      <ul>
+        <li><a href="/">Home</a></li>
+        <li><a href="/about" class="active">About <em>us</em></a></li>
+      </ul>
+    </nav>
+    <div id="main">
+      Hello, <b>world</b>!
+      <pre>  keep
+    this  </pre>
+      <p></p>
+    </div>
+  </body>
+</html>"#;
+    assert_eq!(
+        doc.html_with(&SerializeOptions::pretty()).as_ref(),
+        expected
+    );
+}
+
+#[test]
+fn test_pretty_indent() {
+    let doc = Document::from("<div><p>One</p><p>Two</p></div>");
+    let html = doc
+        .select("div")
+        .html_with(&SerializeOptions::pretty().indent("\t"));
+    assert_eq!(html.as_ref(), "<div>\n\t<p>One</p>\n\t<p>Two</p>\n</div>");
+}
+
+#[test]
+fn test_pretty_textarea() {
+    let doc = Document::from("<form><textarea>  a\n  b</textarea><div>x</div></form>");
+    let html = doc.select("form").html_with(&SerializeOptions::pretty());
+    assert_eq!(
+        html.as_ref(),
+        "<form>\n  <textarea>  a\n  b</textarea>\n  <div>x</div>\n</form>"
+    );
+}
+
+#[test]
+fn test_minified() {
+    let doc = Document::from(PAGE);
+    let expected = concat!(
+        "<!DOCTYPE html><html><head><title>Example</title><style>\n  body { color: red; }\n</style></head>",
+        "<body><nav><ul><li><a href=/>Home</a></li><li><a href=/about class=active>About <em>us</em></a></li></ul></nav>",
+        "<div id=main>Hello, <b>world</b>!<pre>  keep\n    this  </pre><p></p></div></body></html>",
+    );
+    assert_eq!(
+        doc.html_with(&SerializeOptions::minified()).as_ref(),
+        expected
+    );
+}
+
+#[test]
+fn test_minified_attributes() {
+    let doc = Document::from(
+        r#"<input type="checkbox" checked="" value="a b" data-x='say "hi"' title="x&amp;y">"#,
+    );
+    let html = doc.select("input").html_with(&SerializeOptions::minified());
+    assert_eq!(
+        html.as_ref(),
+        r#"<input type=checkbox checked value="a b" data-x="say &quot;hi&quot;" title=x&amp;y>"#
+    );
+}
+
+#[test]
+fn test_minified_keeps_inline_whitespace() {
+    let doc = Document::from("<p> <b>a</b> <i>b</i> c </p>");
+    let html = doc.select("p").html_with(&SerializeOptions::minified());
+    assert_eq!(html.as_ref(), "<p><b>a</b> <i>b</i> c</p>");
+}
@@ -0,0 +1,133 @@
+use nipper::Document;
+
+#[test]
+fn test_selection_xml_void_elements() {
+    let doc =
+        Document::from(r#"<div id="a">One<br>Two<img src="x.png" alt="a &quot;b&quot;"></div>"#);
+    let xml = doc.select("#a").xml();
+    assert_eq!(
+        xml.as_ref(),
+        r#"<div xmlns="http://www.w3.org/1999/xhtml" id="a">One<br/>Two<img src="x.png" alt="a &quot;b&quot;"/></div>"#
+    );
+}
+
+#[test]
+fn test_selection_xhtml() {
+    let doc = Document::from(r#"<p>One<br><span></span><script></script></p>"#);
+    let xhtml = doc.select("p").xhtml();
+    assert_eq!(
+        xhtml.as_ref(),
+        r#"<p xmlns="http://www.w3.org/1999/xhtml">One<br /><span></span><script></script></p>"#
+    );
+}
+
+#[test]
+fn test_xml_escaping() {
+    let doc =
+        Document::from("<p title='a < b & c'>1 &lt; 2 &amp;&amp; 3 &gt; 2<!-- a -- b --></p>");
+    let xml = doc.select("p").xml();
+    assert_eq!(
+        xml.as_ref(),
+        r#"<p xmlns="http://www.w3.org/1999/xhtml" title="a &lt; b &amp; c">1 &lt; 2 &amp;&amp; 3 &gt; 2<!-- a - - b --></p>"#
+    );
+}
+
+#[test]
+fn test_xml_inline_svg_namespaces() {
+    let doc = Document::from(
+        r##"<div><svg viewBox="0 0 10 10"><use xlink:href="#icon"></use></svg></div>"##,
+    );
+    let xml = doc.select("svg").xml();
+    assert_eq!(
+        xml.as_ref(),
+        r##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 10 10"><use xmlns:xlink="http://www.w3.org/1999/xlink" xlink:href="#icon"/></svg>"##
+    );
+}
+
+#[test]
+fn test_document_xml_round_trip() {
+    let source = r#"<?xml version="1.0"?><feed xmlns="http://www.w3.org/2005/Atom" xmlns:media="http://search.yahoo.com/mrss/"><entry><title>A &amp; B</title><media:thumbnail url="a.png"/></entry></feed>"#;
+    let doc = Document::from_xml(source);
+    let xml = doc.xml();
+    assert_eq!(
+        xml.as_ref(),
+        r#"<?xml version="1.0"?><feed xmlns="http://www.w3.org/2005/Atom"><entry><title>A &amp; B</title><media:thumbnail xmlns:media="http://search.yahoo.com/mrss/" url="a.png"/></entry></feed>"#
+    );
+
+    let reparsed = Document::from_xml(xml);
+    assert_eq!(reparsed.select("entry > title").text().as_ref(), "A & B");
+    assert_eq!(reparsed.xml(), doc.xml());
+}
+
+#[test]
+fn test_document_xhtml() {
+    let doc = Document::from(
+        "<!DOCTYPE html><html><head><title>T</title></head><body><hr></body></html>",
+    );
+    assert_eq!(
+        doc.xhtml().as_ref(),
+        r#"<!DOCTYPE html><html xmlns="http://www.w3.org/1999/xhtml"><head><title>T</title></head><body><hr /></body></html>"#
+    );
+}
+
+#[test]
+fn test_empty_selection_xml() {
+    let doc = Document::from("<p>Hello</p>");
+    assert_eq!(doc.select("div").xml().as_ref(), "");
+    assert_eq!(doc.select("div").xhtml().as_ref(), "");
+}
+
+#[test]
+fn test_xhtml_raw_text() {
+    let doc = Document::from(
+        "<script>if (a < b && c) {}</script><style>p > a { color: red }</style><script>go()</script>",
+    );
+    assert_eq!(
+        doc.select("head").xhtml().as_ref(),
+        "<head xmlns=\"http://www.w3.org/1999/xhtml\"><script>//<![CDATA[\nif (a < b && c) {}\n//]]></script><style>p > a { color: red }</style><script>go()</script></head>"
+    );
+
+    let doc = Document::from("<style>a::before { content: \"]]>&\" }</style>");
+    assert_eq!(
+        doc.select("style").xhtml().as_ref(),
+        "<style xmlns=\"http://www.w3.org/1999/xhtml\">/*<![CDATA[*/\na::before { content: \"]]]]><![CDATA[>&\" }\n/*]]>*/</style>"
+    );
+
+    // Plain XML escapes them like any other text.
+    let doc = Document::from("<script>a < b</script>");
+    assert_eq!(
+        doc.select("script").xml().as_ref(),
+        "<script xmlns=\"http://www.w3.org/1999/xhtml\">a &lt; b</script>"
+    );
+}
+
+#[test]
+fn test_xhtml_doctype_ids() {
+    let html = r#"<!DOCTYPE html PUBLIC "-//W3C//DTD XHTML 1.0 Strict//EN" "http://www.w3.org/TR/xhtml1/DTD/xhtml1-strict.dtd"><title>T</title>"#;
+    let doc = Document::from(html);
+    assert!(doc.xhtml().starts_with(
+        r#"<!DOCTYPE html PUBLIC "-//W3C//DTD XHTML 1.0 Strict//EN" "http://www.w3.org/TR/xhtml1/DTD/xhtml1-strict.dtd"><html"#
+    ));
+    assert!(doc.html().starts_with("<!DOCTYPE html><html>"));
+
+    let doc = Document::from(r#"<!DOCTYPE html SYSTEM "about:legacy-compat"><title>T</title>"#);
+    assert!(doc
+        .xml()
+        .starts_with(r#"<!DOCTYPE html SYSTEM "about:legacy-compat"><html"#));
+}
+
+#[test]
+fn test_xml_attribute_whitespace() {
+    let doc = Document::from("<p title=\"a\nb\tc\">x</p>");
+    assert_eq!(
+        doc.select("p").xml().as_ref(),
+        r#"<p xmlns="http://www.w3.org/1999/xhtml" title="a&#10;b&#9;c">x</p>"#
+    );
+
+    let mut p = doc.select("p");
+    p.set_attr("title", "a\r\nb");
+    assert_eq!(
+        p.xhtml().as_ref(),
+        r#"<p xmlns="http://www.w3.org/1999/xhtml" title="a&#13;&#10;b">x</p>"#
+    );
+}